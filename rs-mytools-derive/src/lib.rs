@@ -0,0 +1,286 @@
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Derive `rs_mytools::borrow::SplitBorrow` for a struct with named fields
+///
+/// Generates a `{Name}Parts<'a>` struct holding a `&'a mut` reference to
+/// every field, so disjoint fields can be borrowed mutably at the same time
+/// and the borrow checker still rejects overlapping borrows
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::borrow::SplitBorrow;
+///
+/// #[derive(SplitBorrow)]
+/// struct Editor {
+///     buffer: String,
+///     history: Vec<String>,
+/// }
+///
+/// let mut editor = Editor { buffer: "draft".to_string(), history: Vec::new() };
+/// let EditorParts { buffer, history } = editor.split();
+/// history.push(buffer.clone());
+/// buffer.clear();
+/// assert_eq!(editor.history, ["draft"]);
+/// ```
+#[proc_macro_derive(SplitBorrow)]
+pub fn derive_split_borrow(input: TokenStream) -> TokenStream {
+    match StructDef::parse(input) {
+        Ok(def) => def.split_borrow_impl().parse().expect("generated invalid tokens"),
+        Err(message) => compile_error(&message),
+    }
+}
+
+/// The parts of a struct definition the derive needs
+struct StructDef {
+    vis: String,
+    name: String,
+    /// Generic parameters with their bounds, without the angle brackets
+    params: Vec<String>,
+    /// Generic parameter names as used in a type, like `'a`, `T` or `N`
+    args: Vec<String>,
+    where_clause: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    vis: String,
+    name: String,
+    ty: String,
+}
+
+impl StructDef {
+    fn parse(input: TokenStream) -> Result<Self, String> {
+        let mut tokens = input.into_iter().peekable();
+
+        // Outer attributes and visibility
+        let mut vis = String::new();
+        loop {
+            match tokens.peek() {
+                Some(TokenTree::Punct(p)) if p.as_char() == '#' => {
+                    tokens.next();
+                    tokens.next();
+                }
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                    vis = take_visibility(&mut tokens);
+                }
+                _ => break,
+            }
+        }
+
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => {}
+            _ => return Err("SplitBorrow can only be derived for structs".to_string()),
+        }
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err("expected a struct name".to_string()),
+        };
+
+        // Generic parameters, split at top-level commas
+        let mut generics = Vec::new();
+        if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '<') {
+            tokens.next();
+            let mut depth = 1;
+            let mut after_dash = false;
+            for token in tokens.by_ref() {
+                if let TokenTree::Punct(p) = &token {
+                    match p.as_char() {
+                        '<' => depth += 1,
+                        '>' if !after_dash => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    after_dash = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+                } else {
+                    after_dash = false;
+                }
+                generics.push(token);
+            }
+        }
+        let params: Vec<Vec<TokenTree>> = split_top_level(generics, ',')
+            .into_iter()
+            .map(|param| split_top_level(param, '=').swap_remove(0))
+            .collect();
+        let args = params.iter().map(|param| generic_arg(param)).collect();
+        let params = params.iter().map(|param| tokens_to_string(param)).collect();
+
+        // Where clause and body
+        let mut where_clause = Vec::new();
+        let body = loop {
+            match tokens.next() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => break group,
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    return Err("SplitBorrow requires a struct with named fields".to_string());
+                }
+                Some(TokenTree::Punct(p)) if p.as_char() == ';' => {
+                    return Err("SplitBorrow requires a struct with named fields".to_string());
+                }
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "where" => {}
+                Some(token) => where_clause.push(token),
+                None => return Err("expected a struct body".to_string()),
+            }
+        };
+        let mut where_clause = tokens_to_string(&where_clause);
+        if !where_clause.is_empty() && !where_clause.trim_end().ends_with(',') {
+            where_clause.push(',');
+        }
+
+        let fields = split_top_level(body.stream().into_iter().collect(), ',')
+            .into_iter()
+            .map(Field::parse)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            vis,
+            name,
+            params,
+            args,
+            where_clause,
+            fields,
+        })
+    }
+
+    fn split_borrow_impl(&self) -> String {
+        let lifetime = "'__split";
+        let parts = format!("{}Parts", self.name);
+        let params = self.params.join(", ");
+        let args = self.args.join(", ");
+        let where_clause = &self.where_clause;
+
+        let mut part_fields: String = self
+            .fields
+            .iter()
+            .map(|field| format!("{} {}: &{} mut {},", field.vis, field.name, lifetime, field.ty))
+            .collect();
+        let mut borrows: String = self
+            .fields
+            .iter()
+            .map(|field| format!("{0}: &mut self.{0},", field.name))
+            .collect();
+        // Without fields the lifetime would be unused
+        if self.fields.is_empty() {
+            part_fields = format!("_marker: ::core::marker::PhantomData<&{} mut ()>,", lifetime);
+            borrows = "_marker: ::core::marker::PhantomData,".to_string();
+        }
+
+        format!(
+            "{vis} struct {parts}<{lifetime}, {params}> where {where_clause} {{ {part_fields} }}
+
+            impl<{params}> ::rs_mytools::borrow::SplitBorrow for {name}<{args}> where {where_clause} {{
+                type SplitParts<{lifetime}> = {parts}<{lifetime}, {args}> where Self: {lifetime};
+
+                fn split<{lifetime}>(&{lifetime} mut self) -> Self::SplitParts<{lifetime}>
+                where
+                    Self: {lifetime},
+                {{
+                    {parts} {{ {borrows} }}
+                }}
+            }}",
+            vis = self.vis,
+            name = self.name,
+        )
+    }
+}
+
+impl Field {
+    fn parse(tokens: Vec<TokenTree>) -> Result<Self, String> {
+        let mut tokens = tokens.into_iter().peekable();
+        let mut vis = String::new();
+        loop {
+            match tokens.peek() {
+                Some(TokenTree::Punct(p)) if p.as_char() == '#' => {
+                    tokens.next();
+                    tokens.next();
+                }
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                    vis = take_visibility(&mut tokens);
+                }
+                _ => break,
+            }
+        }
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err("expected a field name".to_string()),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ':' => {}
+            _ => return Err(format!("expected `:` after field `{}`", name)),
+        }
+        let ty: Vec<TokenTree> = tokens.collect();
+        Ok(Self {
+            vis,
+            name,
+            ty: tokens_to_string(&ty),
+        })
+    }
+}
+
+/// Consume `pub` and an optional restriction like `(crate)`
+fn take_visibility(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) -> String {
+    let mut vis = String::from("pub");
+    tokens.next();
+    if let Some(TokenTree::Group(group)) = tokens.peek() {
+        if group.delimiter() == Delimiter::Parenthesis {
+            vis.push_str(&group.to_string());
+            tokens.next();
+        }
+    }
+    vis
+}
+
+/// Split tokens at a separator that is not nested inside angle brackets
+fn split_top_level(tokens: Vec<TokenTree>, separator: char) -> Vec<Vec<TokenTree>> {
+    let mut items = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut after_dash = false;
+    for token in tokens {
+        if let TokenTree::Punct(p) = &token {
+            match p.as_char() {
+                c if c == separator && depth == 0 => {
+                    items.push(Vec::new());
+                    after_dash = false;
+                    continue;
+                }
+                '<' => depth += 1,
+                // The `>` of `->` does not close a bracket
+                '>' if !after_dash => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            after_dash = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+        } else {
+            after_dash = false;
+        }
+        items.last_mut().expect("items is never empty").push(token);
+    }
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// The name a generic parameter is referred to by: `'a`, `T` or `N`
+fn generic_arg(param: &[TokenTree]) -> String {
+    match param {
+        [TokenTree::Punct(p), TokenTree::Ident(lifetime), ..] if p.as_char() == '\'' => {
+            format!("'{}", lifetime)
+        }
+        [TokenTree::Ident(keyword), TokenTree::Ident(name), ..] if keyword.to_string() == "const" => {
+            name.to_string()
+        }
+        [TokenTree::Ident(name), ..] => name.to_string(),
+        _ => tokens_to_string(param),
+    }
+}
+
+fn tokens_to_string(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("::core::compile_error!({:?});", message)
+        .parse()
+        .expect("compile_error! invocation is valid")
+}
@@ -1,5 +1,14 @@
-use std::cell::{Cell, RefCell};
+use std::cell::{BorrowError, BorrowMutError, Cell, RefCell, RefMut, UnsafeCell};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop};
+use std::panic::Location;
 use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock, Weak};
 
 /// A trait for safely taking ownership of a value temporarily and then putting it back
 pub trait TakeReplace<T>: Sized {
@@ -18,6 +27,54 @@ pub trait TakeReplace<T>: Sized {
         let old_val = self.take();
         self.replace(f(old_val));
     }
+
+    /// Update value using a fallible function, restoring the original on error
+    fn try_update<F, E>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnOnce(T) -> Result<T, E>,
+        T: Clone,
+    {
+        let original = self.take();
+        match f(original.clone()) {
+            Ok(updated) => {
+                self.replace(updated);
+                Ok(())
+            }
+            Err(err) => {
+                self.replace(original);
+                Err(err)
+            }
+        }
+    }
+
+    /// Take the value only if it satisfies a predicate, otherwise leave it in place
+    fn take_if<P>(&mut self, pred: P) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool,
+    {
+        let current = self.take();
+        if pred(&current) {
+            Some(current)
+        } else {
+            self.replace(current);
+            None
+        }
+    }
+
+    /// Replace the value only if it satisfies a predicate, returning the old value
+    fn replace_if<P>(&mut self, pred: P, value: T) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool,
+    {
+        let current = self.take();
+        if pred(&current) {
+            self.replace(value);
+            Some(current)
+        } else {
+            self.replace(current);
+            None
+        }
+    }
 }
 
 impl<T: Default> TakeReplace<T> for T {
@@ -30,10 +87,119 @@ impl<T: Default> TakeReplace<T> for T {
     }
 }
 
+/// Extensions for `Arc` that avoid the `try_unwrap` dance
+pub trait ArcExt<T> {
+    /// Take the value out if this is the only reference, otherwise clone it
+    fn unwrap_or_clone(self) -> T
+    where
+        T: Clone;
+
+    /// Get mutable access, cloning the value first if it is shared
+    fn get_mut_or_clone(&mut self) -> &mut T
+    where
+        T: Clone;
+
+    /// Transform the value into a new `Arc`, cloning it only if it is shared
+    fn map_cloned<U, F: FnOnce(T) -> U>(self, f: F) -> Arc<U>
+    where
+        T: Clone;
+}
+
+impl<T> ArcExt<T> for Arc<T> {
+    fn unwrap_or_clone(self) -> T
+    where
+        T: Clone,
+    {
+        Arc::try_unwrap(self).unwrap_or_else(|shared| (*shared).clone())
+    }
+
+    fn get_mut_or_clone(&mut self) -> &mut T
+    where
+        T: Clone,
+    {
+        Arc::make_mut(self)
+    }
+
+    fn map_cloned<U, F: FnOnce(T) -> U>(self, f: F) -> Arc<U>
+    where
+        T: Clone,
+    {
+        Arc::new(f(ArcExt::unwrap_or_clone(self)))
+    }
+}
+
+/// Caches a shared value through a `Weak` reference
+///
+/// The cache never keeps the value alive by itself: once every `Arc` handed
+/// out has been dropped, the next access rebuilds it. Usable in `static` items
+pub struct WeakCache<T> {
+    slot: Mutex<Weak<T>>,
+}
+
+impl<T> WeakCache<T> {
+    /// Create an empty cache
+    pub const fn new() -> Self {
+        Self {
+            slot: Mutex::new(Weak::new()),
+        }
+    }
+
+    /// Get the cached value if it is still alive
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.lock().upgrade()
+    }
+
+    /// Get the cached value, building it with `f` if it has been dropped
+    pub fn get_or_create<F: FnOnce() -> T>(&self, f: F) -> Arc<T> {
+        let mut slot = self.lock();
+        if let Some(value) = slot.upgrade() {
+            return value;
+        }
+        let value = Arc::new(f());
+        *slot = Arc::downgrade(&value);
+        value
+    }
+
+    /// Get the cached value, building it with a fallible `f` if it has been dropped
+    pub fn get_or_try_create<E, F: FnOnce() -> Result<T, E>>(&self, f: F) -> Result<Arc<T>, E> {
+        let mut slot = self.lock();
+        if let Some(value) = slot.upgrade() {
+            return Ok(value);
+        }
+        let value = Arc::new(f()?);
+        *slot = Arc::downgrade(&value);
+        Ok(value)
+    }
+
+    /// Forget the cached value so the next access rebuilds it
+    pub fn clear(&self) {
+        *self.lock() = Weak::new();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Weak<T>> {
+        self.slot.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Default for WeakCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for WeakCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakCache")
+            .field("alive", &(self.lock().strong_count() > 0))
+            .finish()
+    }
+}
+
 /// A wrapper for safely handling self-referential structures
 /// by using interior mutability
 pub struct SelfRef<T> {
     inner: RefCell<T>,
+    scope: Cell<Option<&'static Location<'static>>>,
 }
 
 impl<T> SelfRef<T> {
@@ -41,6 +207,7 @@ impl<T> SelfRef<T> {
     pub fn new(value: T) -> Self {
         Self {
             inner: RefCell::new(value),
+            scope: Cell::new(None),
         }
     }
     
@@ -63,6 +230,53 @@ impl<T> SelfRef<T> {
     pub fn with_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
         f(&mut self.inner.borrow_mut())
     }
+
+    /// Borrow the value immutably, failing if it is mutably borrowed
+    pub fn try_borrow(&self) -> Result<impl Deref<Target = T> + '_, BorrowError> {
+        self.inner.try_borrow()
+    }
+
+    /// Borrow the value mutably, failing if it is already borrowed
+    pub fn try_borrow_mut(&self) -> Result<impl DerefMut<Target = T> + '_, BorrowMutError> {
+        self.inner.try_borrow_mut()
+    }
+
+    /// Apply a function to the value, failing if it is mutably borrowed
+    pub fn try_with<R, F: FnOnce(&T) -> R>(&self, f: F) -> Result<R, BorrowError> {
+        Ok(f(&*self.inner.try_borrow()?))
+    }
+
+    /// Mutate the value, recording the caller so re-entrant borrows can be reported
+    ///
+    /// Panics if the value is already borrowed, naming the location of the
+    /// `borrow_scope` call that holds it when there is one
+    #[track_caller]
+    pub fn borrow_scope<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        let caller = Location::caller();
+        let Ok(mut value) = self.inner.try_borrow_mut() else {
+            match self.scope.get() {
+                Some(holder) => panic!(
+                    "SelfRef borrowed at {} while already borrowed by the scope at {}",
+                    caller, holder
+                ),
+                None => panic!("SelfRef borrowed at {} while already borrowed", caller),
+            }
+        };
+        let outer = self.scope.replace(Some(caller));
+        let _restore = ScopeGuard::new(|| self.scope.set(outer));
+        f(&mut value)
+    }
+
+    /// Apply a fallible mutation, restoring the original value if it returns an error
+    pub fn try_with_mut<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+        T: Clone,
+    {
+        let mut value = self.inner.borrow_mut();
+        let original = value.clone();
+        f(&mut value).inspect_err(|_| *value = original)
+    }
 }
 
 impl<T: Default> Default for SelfRef<T> {
@@ -71,6 +285,185 @@ impl<T: Default> Default for SelfRef<T> {
     }
 }
 
+/// A thread-safe `SelfRef` backed by an `RwLock`
+///
+/// The plain accessors panic if another thread panicked while holding the
+/// lock. The `_ignore_poison` variants recover the value instead
+pub struct SyncSelfRef<T> {
+    inner: RwLock<T>,
+}
+
+impl<T> SyncSelfRef<T> {
+    /// Create a new thread-safe wrapper
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: RwLock::new(value),
+        }
+    }
+
+    /// Borrow the value immutably, blocking while a writer holds the lock
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.inner.read().expect("SyncSelfRef lock poisoned")
+    }
+
+    /// Borrow the value mutably, blocking until no other borrows are held
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.inner.write().expect("SyncSelfRef lock poisoned")
+    }
+
+    /// Apply a function to the value that returns a result
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.borrow())
+    }
+
+    /// Apply a function that mutates the value
+    pub fn with_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.borrow_mut())
+    }
+
+    /// Like `with`, but recovers the value if the lock is poisoned
+    pub fn with_ignore_poison<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.inner.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Like `with_mut`, but recovers the value if the lock is poisoned
+    pub fn with_mut_ignore_poison<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.inner.write().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Returns true if a thread panicked while holding the lock
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    /// Mark the value as usable again after a panic
+    pub fn clear_poison(&self) {
+        self.inner.clear_poison();
+    }
+
+    /// Consume the wrapper and return the value, even if the lock is poisoned
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Default> Default for SyncSelfRef<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// A borrow token that governs every `TokenCell` with the same brand
+///
+/// Each token gets a unique `'id` lifetime from `Token::scope`, so a cell
+/// can only be accessed with the token it was created for. Holding `&Token`
+/// grants shared access to all its cells and `&mut Token` grants mutable
+/// access to one at a time, all checked at compile time
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::borrow::{Token, TokenCell};
+///
+/// Token::scope(|mut token| {
+///     let total = TokenCell::new(1);
+///     let step = TokenCell::new(2);
+///     *total.borrow_mut(&mut token) += *step.borrow(&token);
+///     assert_eq!(*total.borrow(&token), 3);
+/// });
+/// ```
+///
+/// A cell branded by one token cannot be opened with another
+///
+/// ```compile_fail
+/// use rs_mytools::borrow::{Token, TokenCell};
+///
+/// Token::scope(|first| {
+///     Token::scope(|second| {
+///         let cell = TokenCell::new(1);
+///         let _ = cell.borrow(&first);
+///         let _ = cell.borrow(&second);
+///     })
+/// });
+/// ```
+///
+/// Nor can a token outlive its scope and be reused
+///
+/// ```compile_fail
+/// use rs_mytools::borrow::Token;
+///
+/// let escaped = Token::scope(|token| token);
+/// ```
+///
+/// And a unique token hands out only one mutable borrow at a time
+///
+/// ```compile_fail
+/// use rs_mytools::borrow::{Token, TokenCell};
+///
+/// Token::scope(|mut token| {
+///     let x = TokenCell::new(1);
+///     let y = TokenCell::new(2);
+///     let a = x.borrow_mut(&mut token);
+///     let b = y.borrow_mut(&mut token);
+///     *a += *b;
+/// });
+/// ```
+pub struct Token<'id> {
+    _brand: PhantomData<fn(&'id ()) -> &'id ()>,
+}
+
+impl Token<'_> {
+    /// Run `f` with a fresh token whose brand cannot escape the closure
+    pub fn scope<R>(f: impl for<'new> FnOnce(Token<'new>) -> R) -> R {
+        f(Token { _brand: PhantomData })
+    }
+}
+
+/// A cell whose contents are borrowed through a `Token` rather than runtime checks
+pub struct TokenCell<'id, T: ?Sized> {
+    _brand: PhantomData<fn(&'id ()) -> &'id ()>,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: access requires the token, which follows the usual borrowing
+// rules, so sharing the cell is as safe as sharing `&T` or `&mut T`
+unsafe impl<T: ?Sized + Send + Sync> Sync for TokenCell<'_, T> {}
+
+impl<'id, T> TokenCell<'id, T> {
+    /// Create a cell branded with the token's lifetime
+    pub const fn new(value: T) -> Self {
+        Self {
+            _brand: PhantomData,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consume the cell and return its value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<'id, T: ?Sized> TokenCell<'id, T> {
+    /// Borrow the value immutably through a shared token
+    pub fn borrow<'a>(&'a self, _token: &'a Token<'id>) -> &'a T {
+        // SAFETY: no `&mut T` can exist while the token is shared
+        unsafe { &*self.value.get() }
+    }
+
+    /// Borrow the value mutably through a unique token
+    pub fn borrow_mut<'a>(&'a self, _token: &'a mut Token<'id>) -> &'a mut T {
+        // SAFETY: the token is exclusively borrowed, so no other borrow of
+        // any cell with this brand can exist
+        unsafe { &mut *self.value.get() }
+    }
+
+    /// Get mutable access without a token when the cell itself is unique
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
 /// A trait for temporarily borrowing multiple fields without 
 /// running into borrow checker conflicts
 pub trait SplitBorrow {
@@ -83,6 +476,10 @@ pub trait SplitBorrow {
     type SplitParts<'a> where Self: 'a;
 }
 
+/// Derive `SplitBorrow` for structs with named fields
+#[cfg(feature = "derive")]
+pub use rs_mytools_derive::SplitBorrow;
+
 // Implementation for a 2-field struct
 impl<T: 'static, U: 'static> SplitBorrow for (T, U) {
     type SplitParts<'a> = (&'a mut T, &'a mut U) where T: 'a, U: 'a;
@@ -123,40 +520,90 @@ impl<'a, T> FieldSplit<'a, T> {
     }
 }
 
-/// A clone-on-write wrapper for types that don't implement Clone
-/// Useful when you need to make copies selectively during runtime
-pub struct CowCell<T> {
-    inner: RefCell<T>,
+/// A clone-on-write cell that shares its value with outstanding snapshots
+///
+/// `get` hands out cheap `Rc` snapshots. Mutable access clones the value
+/// only while a snapshot is still alive, so the snapshot keeps seeing the
+/// old value; otherwise the value is changed in place
+pub struct CowCell<T: Clone> {
+    inner: RefCell<Rc<T>>,
+    clones: Cell<usize>,
 }
 
-impl<T> CowCell<T> {
+impl<T: Clone> CowCell<T> {
     /// Create a new clone-on-write cell
     pub fn new(value: T) -> Self {
         Self {
-            inner: RefCell::new(value),
+            inner: RefCell::new(Rc::new(value)),
+            clones: Cell::new(0),
         }
     }
     
-    /// Get immutable access to the value
-    pub fn get(&self) -> impl Deref<Target = T> + '_ {
-        self.inner.borrow()
+    /// Get a snapshot of the current value
+    pub fn get(&self) -> Rc<T> {
+        Rc::clone(&self.inner.borrow())
     }
     
-    /// Get mutable access to the value
+    /// Get mutable access to the value, cloning it first if a snapshot is alive
     pub fn get_mut(&self) -> impl DerefMut<Target = T> + '_ {
-        self.inner.borrow_mut()
+        let mut shared = self.inner.borrow_mut();
+        self.unshare(&mut shared, T::clone);
+        RefMut::map(shared, |shared| {
+            Rc::get_mut(shared).expect("value is unshared")
+        })
+    }
+
+    /// Get mutable access through a unique reference, cloning only if a snapshot is alive
+    pub fn make_mut(&mut self) -> &mut T {
+        let shared = self.inner.get_mut();
+        if Rc::strong_count(shared) > 1 {
+            self.clones.set(self.clones.get() + 1);
+        }
+        Rc::make_mut(shared)
     }
     
-    /// Apply an operation that may need to clone the value
+    /// Apply an operation, cloning with `clone_fn` only if a snapshot is alive
     pub fn with_cow<R, F, C>(&self, clone_fn: C, f: F) -> R
     where
         F: FnOnce(&mut T) -> R,
         C: FnOnce(&T) -> T,
     {
-        let cloned = clone_fn(&self.inner.borrow());
-        let mut borrowed = self.inner.borrow_mut();
-        *borrowed = cloned;
-        f(&mut borrowed)
+        let mut shared = self.inner.borrow_mut();
+        self.unshare(&mut shared, clone_fn);
+        f(Rc::get_mut(&mut shared).expect("value is unshared"))
+    }
+
+    /// Returns true if a mutation has ever had to clone the value
+    pub fn is_cloned(&self) -> bool {
+        self.clones.get() > 0
+    }
+
+    /// The number of times a mutation has had to clone the value
+    pub fn clone_count(&self) -> usize {
+        self.clones.get()
+    }
+
+    /// Returns true if a snapshot of the current value is still alive
+    pub fn is_shared(&self) -> bool {
+        Rc::strong_count(&self.inner.borrow()) > 1
+    }
+
+    /// Consume the cell and return the value, cloning it if a snapshot is alive
+    pub fn into_inner(self) -> T {
+        Rc::try_unwrap(self.inner.into_inner()).unwrap_or_else(|shared| (*shared).clone())
+    }
+
+    fn unshare<C: FnOnce(&T) -> T>(&self, shared: &mut Rc<T>, clone_fn: C) {
+        if Rc::strong_count(shared) > 1 {
+            *shared = Rc::new(clone_fn(shared));
+            self.clones.set(self.clones.get() + 1);
+        }
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for CowCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CowCell").field(&*self.get()).finish()
     }
 }
 
@@ -185,3 +632,616 @@ impl<T: Copy> MutShared<T> {
         self.set(f(old));
     }
 }
+
+/// Types without padding or other uninitialized bytes
+///
+/// `AtomicCell::new` stores these in a native atomic integer when one fits.
+/// The trait is sealed; for other padding-free types use the `unsafe`
+/// `AtomicCell::new_lock_free`
+pub trait NoUninit: Copy + sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_no_uninit {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl NoUninit for $ty {}
+        )*
+    };
+}
+
+impl_no_uninit!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+impl<T: ?Sized> sealed::Sealed for *const T {}
+impl<T: ?Sized> NoUninit for *const T {}
+impl<T: ?Sized> sealed::Sealed for *mut T {}
+impl<T: ?Sized> NoUninit for *mut T {}
+
+/// A thread-safe counterpart to `MutShared`
+///
+/// Cells holding a `NoUninit` type created with `new`, or created with
+/// `new_lock_free`, are stored in a native atomic integer when the type's
+/// size and alignment fit one, so every operation is lock-free. Everything
+/// else, including any cell created with `new_locked`, is guarded by a small
+/// spin lock
+pub struct AtomicCell<T: Copy> {
+    value: UnsafeCell<T>,
+    locked: AtomicBool,
+    lock_free: bool,
+}
+
+// SAFETY: all access to `value` goes through an atomic or the spin lock, and
+// values are copied out to other threads, so `T` must be both `Send` and `Sync`
+unsafe impl<T: Copy + Send + Sync> Sync for AtomicCell<T> {}
+
+// Run `$body` with `$atomic` bound to the first atomic type that can hold a
+// `T`, or evaluate `$fallback` if the cell is locked or none fits
+macro_rules! with_atomic {
+    ($cell:expr, $atomic:ident => $body:expr, $fallback:expr) => {{
+        if $cell.lock_free {
+            with_atomic!(@try $cell, AtomicU8, $atomic => $body);
+            with_atomic!(@try $cell, AtomicU16, $atomic => $body);
+            with_atomic!(@try $cell, AtomicU32, $atomic => $body);
+            with_atomic!(@try $cell, AtomicU64, $atomic => $body);
+        }
+        $fallback
+    }};
+    (@try $cell:expr, $ty:ident, $atomic:ident => $body:expr) => {
+        if Self::fits::<$ty>() {
+            // SAFETY: the cell is suitably sized and aligned for the atomic,
+            // and is only ever accessed through it
+            let $atomic = unsafe { &*$cell.value.get().cast::<$ty>() };
+            return $body;
+        }
+    };
+}
+
+impl<T: NoUninit> AtomicCell<T> {
+    /// Create a new cell, lock-free when a native atomic fits the type
+    pub const fn new(value: T) -> Self {
+        // SAFETY: `NoUninit` types have no padding or uninitialized bytes
+        unsafe { Self::new_lock_free(value) }
+    }
+}
+
+impl<T: Copy> AtomicCell<T> {
+    /// Create a new cell guarded by a spin lock
+    pub const fn new_locked(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            locked: AtomicBool::new(false),
+            lock_free: false,
+        }
+    }
+
+    /// Create a new cell that stores the value in a native atomic when one fits
+    ///
+    /// # Safety
+    ///
+    /// `T` must not contain padding bytes or any other uninitialized bytes,
+    /// such as a `#[repr(align(8))]` wrapper around a `u8`. Primitive
+    /// integers, `bool`, `char`, floats and raw pointers are fine, though
+    /// those already implement `NoUninit` and can use `new`
+    pub const unsafe fn new_lock_free(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            locked: AtomicBool::new(false),
+            lock_free: true,
+        }
+    }
+
+    /// Returns true if operations on this cell are lock-free
+    pub fn is_lock_free(&self) -> bool {
+        self.lock_free
+            && (Self::fits::<AtomicU8>()
+                || Self::fits::<AtomicU16>()
+                || Self::fits::<AtomicU32>()
+                || Self::fits::<AtomicU64>())
+    }
+
+    fn fits<A>() -> bool {
+        mem::size_of::<T>() == mem::size_of::<A>() && mem::align_of::<T>() >= mem::align_of::<A>()
+    }
+
+    /// Get the current value
+    pub fn get(&self) -> T {
+        with_atomic!(self, atomic => {
+            // SAFETY: the atomic only ever holds the bytes of a valid `T`
+            unsafe { mem::transmute_copy(&atomic.load(Ordering::Acquire)) }
+        }, {
+            let _lock = self.lock();
+            // SAFETY: the spin lock is held
+            unsafe { *self.value.get() }
+        })
+    }
+
+    /// Set a new value
+    pub fn set(&self, value: T) {
+        self.swap(value);
+    }
+
+    /// Set a new value and return the previous one
+    pub fn swap(&self, value: T) -> T {
+        with_atomic!(self, atomic => {
+            // SAFETY: `T` and the atomic's integer have the same size, and
+            // the constructor guarantees `T` has no uninitialized bytes
+            unsafe { mem::transmute_copy(&atomic.swap(mem::transmute_copy(&value), Ordering::AcqRel)) }
+        }, {
+            let _lock = self.lock();
+            // SAFETY: the spin lock is held
+            unsafe { mem::replace(&mut *self.value.get(), value) }
+        })
+    }
+
+    /// Update the value using a function and return the new value
+    ///
+    /// On the lock-free path `f` is retried if another thread changed the
+    /// value concurrently, so it may be called more than once
+    pub fn update<F: FnMut(T) -> T>(&self, mut f: F) -> T {
+        with_atomic!(self, atomic => {
+            let mut current = atomic.load(Ordering::Acquire);
+            loop {
+                // SAFETY: the atomic only ever holds the bytes of a valid `T`,
+                // and the constructor guarantees those bytes are all initialized
+                let new = f(unsafe { mem::transmute_copy(&current) });
+                let bits = unsafe { mem::transmute_copy(&new) };
+                match atomic.compare_exchange_weak(current, bits, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => break new,
+                    Err(actual) => current = actual,
+                }
+            }
+        }, {
+            let _lock = self.lock();
+            // SAFETY: the spin lock is held, and is released even if `f` panics
+            let value = unsafe { &mut *self.value.get() };
+            *value = f(*value);
+            *value
+        })
+    }
+
+    /// Consume the cell and return its value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    fn lock(&self) -> SpinGuard<'_> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        SpinGuard(&self.locked)
+    }
+}
+
+struct SpinGuard<'a>(&'a AtomicBool);
+
+impl Drop for SpinGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl<T: NoUninit + Default> Default for AtomicCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for AtomicCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicCell").field(&self.get()).finish()
+    }
+}
+
+/// A value that is only changed through all-or-nothing transactions
+///
+/// Each transaction works on a clone of the value, which replaces the
+/// original only if the closure returns `Ok`. On `Err` or panic the
+/// original is left untouched
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transactional<T: Clone> {
+    value: T,
+}
+
+impl<T: Clone> Transactional<T> {
+    /// Wrap a value
+    pub fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    /// Apply mutations to a copy of the value and commit them only on `Ok`
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut draft = self.value.clone();
+        let result = f(&mut draft)?;
+        self.value = draft;
+        Ok(result)
+    }
+
+    /// Consume the wrapper and return the committed value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Clone> Deref for Transactional<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Runs a closure when dropped, unless it has been dismissed
+///
+/// The closure also runs during unwinding, so cleanup happens on
+/// early return and on panic alike. A guard created with `with_state` owns
+/// a value that it derefs to and hands to the closure, and `into_inner`
+/// takes that value back without running the cleanup
+pub struct ScopeGuard<T, F: Cleanup<T>> {
+    state: ManuallyDrop<T>,
+    on_drop: ManuallyDrop<F>,
+}
+
+/// A cleanup action that a `ScopeGuard` runs with its state
+pub trait Cleanup<T> {
+    /// Run the cleanup, consuming the guarded state
+    fn run(self, state: T);
+}
+
+impl<T, F: FnOnce(T)> Cleanup<T> for F {
+    fn run(self, state: T) {
+        self(state)
+    }
+}
+
+/// Adapts a closure without arguments into the cleanup of a stateless `ScopeGuard`
+pub struct Deferred<F>(F);
+
+impl<F: FnOnce()> Cleanup<()> for Deferred<F> {
+    fn run(self, (): ()) {
+        (self.0)()
+    }
+}
+
+impl<F: FnOnce()> ScopeGuard<(), Deferred<F>> {
+    /// Create a guard that calls `on_drop` when it goes out of scope
+    pub fn new(on_drop: F) -> Self {
+        Self::from_parts((), Deferred(on_drop))
+    }
+}
+
+impl<T, F: FnOnce(T)> ScopeGuard<T, F> {
+    /// Create a guard that owns `state` and passes it to `on_drop` when it goes out of scope
+    pub fn with_state(state: T, on_drop: F) -> Self {
+        Self::from_parts(state, on_drop)
+    }
+}
+
+impl<T, F: Cleanup<T>> ScopeGuard<T, F> {
+    fn from_parts(state: T, on_drop: F) -> Self {
+        Self {
+            state: ManuallyDrop::new(state),
+            on_drop: ManuallyDrop::new(on_drop),
+        }
+    }
+
+    /// Cancel the cleanup so the closure never runs
+    pub fn dismiss(self) {
+        drop(self.into_inner());
+    }
+
+    /// Cancel the cleanup and take back the guarded state
+    pub fn into_inner(self) -> T {
+        let mut guard = ManuallyDrop::new(self);
+        // SAFETY: the guard is never dropped, so each field is taken exactly once
+        unsafe {
+            ManuallyDrop::drop(&mut guard.on_drop);
+            ManuallyDrop::take(&mut guard.state)
+        }
+    }
+}
+
+impl<T, F: Cleanup<T>> Deref for ScopeGuard<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.state
+    }
+}
+
+impl<T, F: Cleanup<T>> DerefMut for ScopeGuard<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+
+impl<T, F: Cleanup<T>> Drop for ScopeGuard<T, F> {
+    fn drop(&mut self) {
+        // SAFETY: drop runs at most once and the fields are not used afterwards
+        let (state, on_drop) = unsafe {
+            (ManuallyDrop::take(&mut self.state), ManuallyDrop::take(&mut self.on_drop))
+        };
+        on_drop.run(state);
+    }
+}
+
+/// Sets environment variables and restores their previous values when dropped
+///
+/// Restoration also happens during unwinding. The environment is shared by
+/// the whole process, so guards on different threads can still interfere
+#[derive(Debug, Default)]
+pub struct EnvGuard {
+    saved: Vec<(OsString, Option<OsString>)>,
+}
+
+impl EnvGuard {
+    /// Create a guard that has not changed anything yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a variable, remembering its original value
+    pub fn set_var<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, value: V) -> &mut Self {
+        self.save(key.as_ref());
+        env::set_var(key, value);
+        self
+    }
+
+    /// Remove a variable, remembering its original value
+    pub fn remove_var<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.save(key.as_ref());
+        env::remove_var(key);
+        self
+    }
+
+    fn save(&mut self, key: &OsStr) {
+        if !self.saved.iter().any(|(saved, _)| saved == key) {
+            self.saved.push((key.to_os_string(), env::var_os(key)));
+        }
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for (key, value) in self.saved.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+
+/// A value computed on first access and cached for the lifetime of the `Lazy`
+///
+/// Thread-safe and usable in `static` items; see the `lazy!` macro. The
+/// initializer can be any `FnOnce`, and defaults to a function pointer so
+/// the type can be named in statics. If it panics, later accesses panic too
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceLock<T>,
+    init: Mutex<Option<F>>,
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Create a lazy value that will be computed by `init`
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: OnceLock::new(),
+            init: Mutex::new(Some(init)),
+        }
+    }
+
+    /// Compute the value if needed and return a reference to it
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| {
+            let init = this.init.lock().unwrap_or_else(PoisonError::into_inner).take();
+            match init {
+                Some(init) => init(),
+                None => panic!("Lazy initializer panicked on an earlier access"),
+            }
+        })
+    }
+}
+
+impl<T, F> Lazy<T, F> {
+    /// Get the value if it has already been computed
+    pub fn get(this: &Self) -> Option<&T> {
+        this.cell.get()
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Lazy::get(self) {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
+
+/// A cell that is written at most once, like `OnceLock` with fallible initialization
+///
+/// Concurrent callers of `get_or_init` and `get_or_try_init` block until the
+/// first initializer finishes, so only one of them runs at a time
+pub struct Once<T> {
+    cell: OnceLock<T>,
+    init_lock: Mutex<()>,
+}
+
+impl<T> Once<T> {
+    /// Create an empty cell
+    pub const fn new() -> Self {
+        Self {
+            cell: OnceLock::new(),
+            init_lock: Mutex::new(()),
+        }
+    }
+
+    /// Get the value if it has been set
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+
+    /// Get mutable access to the value if it has been set
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.cell.get_mut()
+    }
+
+    /// Set the value, handing it back if the cell was already full
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let _lock = self.init_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.cell.set(value)
+    }
+
+    /// Get the value, computing it with `f` if the cell is empty
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        match self.get_or_try_init(|| Ok::<T, std::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Get the value, computing it with `f` if the cell is empty
+    ///
+    /// If `f` fails the cell stays empty and the error is returned
+    pub fn get_or_try_init<E, F: FnOnce() -> Result<T, E>>(&self, f: F) -> Result<&T, E> {
+        if let Some(value) = self.cell.get() {
+            return Ok(value);
+        }
+        let _lock = self.init_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = self.cell.get() {
+            return Ok(value);
+        }
+        let value = f()?;
+        Ok(self.cell.get_or_init(|| value))
+    }
+
+    /// Take the value out, leaving the cell empty
+    pub fn take(&mut self) -> Option<T> {
+        self.cell.take()
+    }
+
+    /// Consume the cell and return its value
+    pub fn into_inner(self) -> Option<T> {
+        self.cell.into_inner()
+    }
+}
+
+impl<T> Default for Once<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Once<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("Once").field(value).finish(),
+            None => f.write_str("Once(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    const THREADS: u64 = 8;
+    const ROUNDS: u64 = 1000;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(C, align(4))]
+    struct Pair(u16, u16);
+
+    fn race_increments<T: Copy + Send + Sync>(cell: &AtomicCell<T>, step: impl Fn(T) -> T + Sync) {
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    for _ in 0..ROUNDS {
+                        cell.update(&step);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn atomic_cell_picks_the_lock_free_path_for_primitives() {
+        assert!(AtomicCell::new(0u8).is_lock_free());
+        assert!(AtomicCell::new(0u64).is_lock_free());
+        assert!(AtomicCell::new(false).is_lock_free());
+        assert!(AtomicCell::new(std::ptr::null::<u8>()).is_lock_free());
+        assert!(!AtomicCell::new(0u128).is_lock_free());
+        assert!(!AtomicCell::new_locked(0u32).is_lock_free());
+        // SAFETY: `Pair` is two `u16`s with no padding
+        assert!(unsafe { AtomicCell::new_lock_free(Pair(0, 0)) }.is_lock_free());
+    }
+
+    #[test]
+    fn atomic_cell_update_races_on_the_lock_free_path() {
+        let cell = AtomicCell::new(0u64);
+        race_increments(&cell, |n| n + 1);
+        assert_eq!(cell.get(), THREADS * ROUNDS);
+    }
+
+    #[test]
+    fn atomic_cell_update_races_on_the_locked_path() {
+        let cell = AtomicCell::new_locked(0u64);
+        race_increments(&cell, |n| n + 1);
+        assert_eq!(cell.into_inner(), THREADS * ROUNDS);
+
+        let wide = AtomicCell::new(0u128);
+        race_increments(&wide, |n| n + 1);
+        assert_eq!(wide.get(), u128::from(THREADS * ROUNDS));
+    }
+
+    #[test]
+    fn atomic_cell_update_races_on_a_lock_free_user_type() {
+        // SAFETY: `Pair` is two `u16`s with no padding
+        let cell = unsafe { AtomicCell::new_lock_free(Pair(0, 0)) };
+        race_increments(&cell, |Pair(low, high)| Pair(low + 1, high + 2));
+        let total = (THREADS * ROUNDS) as u16;
+        assert_eq!(cell.get(), Pair(total, total * 2));
+    }
+
+    #[test]
+    fn atomic_cell_swap_returns_the_previous_value() {
+        for cell in [AtomicCell::new(1i32), AtomicCell::new_locked(1i32)] {
+            assert_eq!(cell.swap(2), 1);
+            cell.set(3);
+            assert_eq!(cell.update(|n| n * 2), 6);
+            assert_eq!(cell.get(), 6);
+        }
+    }
+
+    #[test]
+    fn atomic_cell_lock_is_released_when_update_panics() {
+        let cell = AtomicCell::new_locked(1u32);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.update(|_| panic!("update failed"));
+        }));
+        assert!(result.is_err());
+        assert_eq!(cell.get(), 1);
+        cell.set(2);
+        assert_eq!(cell.get(), 2);
+    }
+}
@@ -1,3 +1,22 @@
+pub mod graph;
+
+use crate::collections::graph::{CycleError, DiGraph};
+use crate::iter::{IterExt, Unzip};
+use crate::random::Rng;
+use std::borrow::Borrow;
+use std::collections::hash_map::Entry;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::mem::{self, MaybeUninit};
+use std::ops::{
+    BitAnd, BitOr, BitXor, Bound, Deref, DerefMut, Index, IndexMut, Range, RangeBounds,
+};
+use std::ptr;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
 pub trait VecExt<T> {
     /// Returns true if the vector is empty or contains only elements that satisfy the predicate
     fn all_or_empty<F>(&self, predicate: F) -> bool
@@ -9,6 +28,52 @@ pub trait VecExt<T> {
     
     /// Safe way to get the last element as an Option
     fn last_option(&self) -> Option<&T>;
+
+    /// Remove all elements matching the predicate and return them
+    /// Both the removed and the remaining elements keep their relative order
+    fn extract_where<F>(&mut self, predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Remove all elements matching the predicate using `swap_remove`
+    /// Faster than `extract_where` but neither side keeps its order
+    fn swap_remove_where<F>(&mut self, predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Remove all duplicate elements, not just adjacent ones, keeping first occurrences
+    fn dedup_unordered(&mut self)
+    where
+        T: Eq + Hash;
+
+    /// Remove all elements whose key was already seen, keeping first occurrences
+    fn dedup_by_key_unordered<K, F>(&mut self, key: F)
+    where
+        K: Eq + Hash,
+        F: FnMut(&T) -> K;
+
+    /// Reorder the vector so matching elements come first and return the split index
+    /// Matching elements keep their relative order; the rest may be reordered
+    fn partition_in_place<F>(&mut self, predicate: F) -> usize
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Keep the matching elements and return the non-matching ones as a new Vec
+    fn split_off_where<F>(&mut self, predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Split a Vec of tuples into one Vec per tuple position
+    fn unzip_n(self) -> T::Output
+    where
+        T: Unzip,
+        Self: Sized;
+
+    /// Distribute the elements in order into exactly `n` groups whose sizes differ by at most one
+    /// Earlier groups receive the extra elements; groups may be empty if `n` exceeds the length
+    fn split_into(self, n: usize) -> Vec<Vec<T>>
+    where
+        Self: Sized;
 }
 
 impl<T> VecExt<T> for Vec<T> {
@@ -26,6 +91,342 @@ impl<T> VecExt<T> for Vec<T> {
     fn last_option(&self) -> Option<&T> {
         self.last()
     }
+
+    fn extract_where<F>(&mut self, mut predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut extracted = Vec::new();
+        let mut kept = Vec::with_capacity(self.len());
+        for item in self.drain(..) {
+            if predicate(&item) {
+                extracted.push(item);
+            } else {
+                kept.push(item);
+            }
+        }
+        *self = kept;
+        extracted
+    }
+
+    fn swap_remove_where<F>(&mut self, mut predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut extracted = Vec::new();
+        let mut i = 0;
+        while i < self.len() {
+            if predicate(&self[i]) {
+                extracted.push(self.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        extracted
+    }
+
+    fn dedup_unordered(&mut self)
+    where
+        T: Eq + Hash,
+    {
+        let keep: Vec<bool> = {
+            let mut seen = HashSet::with_capacity(self.len());
+            self.iter().map(|item| seen.insert(item)).collect()
+        };
+        let mut index = 0;
+        self.retain(|_| {
+            index += 1;
+            keep[index - 1]
+        });
+    }
+
+    fn dedup_by_key_unordered<K, F>(&mut self, mut key: F)
+    where
+        K: Eq + Hash,
+        F: FnMut(&T) -> K,
+    {
+        let mut seen = HashSet::with_capacity(self.len());
+        self.retain(|item| seen.insert(key(item)));
+    }
+
+    fn partition_in_place<F>(&mut self, mut predicate: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut split = 0;
+        for i in 0..self.len() {
+            if predicate(&self[i]) {
+                self.swap(split, i);
+                split += 1;
+            }
+        }
+        split
+    }
+
+    fn split_off_where<F>(&mut self, predicate: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let split = self.partition_in_place(predicate);
+        self.split_off(split)
+    }
+
+    fn unzip_n(self) -> T::Output
+    where
+        T: Unzip,
+    {
+        T::unzip_all(self.into_iter())
+    }
+
+    fn split_into(self, n: usize) -> Vec<Vec<T>> {
+        assert!(n > 0, "n must be greater than 0");
+        let (base, extra) = (self.len() / n, self.len() % n);
+        let mut items = self.into_iter();
+        (0..n)
+            .map(|i| items.by_ref().take(base + usize::from(i < extra)).collect())
+            .collect()
+    }
+}
+
+/// Extensions for slices
+pub trait SliceExt<T> {
+    /// Iterate over windows of `size` elements, advancing `step` elements each time
+    /// Trailing elements that don't fill a whole window are skipped
+    fn windows_step(&self, size: usize, step: usize) -> StridedWindows<'_, T>;
+
+    /// Iterate over chunks of `size` elements where consecutive chunks share `overlap` elements
+    /// The last chunk may be shorter so that every element is covered
+    fn chunks_overlapping(&self, size: usize, overlap: usize) -> StridedWindows<'_, T>;
+
+    /// Shuffle the elements in place using a freshly seeded `Rng`
+    fn shuffle(&mut self);
+
+    /// Shuffle the elements in place using the given generator
+    fn shuffle_with(&mut self, rng: &mut Rng);
+
+    /// Pick `n` distinct elements at random using a freshly seeded `Rng`
+    /// Returns every element (in random order) if `n` exceeds the length
+    fn choose(&self, n: usize) -> Vec<&T>;
+
+    /// Pick `n` distinct elements at random using the given generator
+    fn choose_with(&self, n: usize, rng: &mut Rng) -> Vec<&T>;
+
+    /// Split into exactly `n` consecutive sub-slices whose lengths differ by at most one
+    /// Earlier sub-slices are the longer ones; some may be empty if `n` exceeds the length
+    fn chunks_balanced(&self, n: usize) -> Vec<&[T]>;
+}
+
+impl<T> SliceExt<T> for [T] {
+    fn windows_step(&self, size: usize, step: usize) -> StridedWindows<'_, T> {
+        assert!(size > 0, "size must be greater than 0");
+        assert!(step > 0, "step must be greater than 0");
+        StridedWindows {
+            slice: self,
+            size,
+            step,
+            pos: 0,
+            allow_partial: false,
+        }
+    }
+
+    fn chunks_overlapping(&self, size: usize, overlap: usize) -> StridedWindows<'_, T> {
+        assert!(size > 0, "size must be greater than 0");
+        assert!(overlap < size, "overlap must be smaller than size");
+        StridedWindows {
+            slice: self,
+            size,
+            step: size - overlap,
+            pos: 0,
+            allow_partial: true,
+        }
+    }
+
+    fn shuffle(&mut self) {
+        self.shuffle_with(&mut Rng::new());
+    }
+
+    fn shuffle_with(&mut self, rng: &mut Rng) {
+        for i in (1..self.len()).rev() {
+            self.swap(i, rng.below(i + 1));
+        }
+    }
+
+    fn choose(&self, n: usize) -> Vec<&T> {
+        self.choose_with(n, &mut Rng::new())
+    }
+
+    fn choose_with(&self, n: usize, rng: &mut Rng) -> Vec<&T> {
+        let n = n.min(self.len());
+        let mut indices: Vec<usize> = (0..self.len()).collect();
+        for i in 0..n {
+            let j = rng.range(i..indices.len());
+            indices.swap(i, j);
+        }
+        indices[..n].iter().map(|&index| &self[index]).collect()
+    }
+
+    fn chunks_balanced(&self, n: usize) -> Vec<&[T]> {
+        assert!(n > 0, "n must be greater than 0");
+        let (base, extra) = (self.len() / n, self.len() % n);
+        let mut start = 0;
+        (0..n)
+            .map(|i| {
+                let end = start + base + usize::from(i < extra);
+                let chunk = &self[start..end];
+                start = end;
+                chunk
+            })
+            .collect()
+    }
+}
+
+/// Iterator over sub-slices of a fixed size taken at a fixed stride
+pub struct StridedWindows<'a, T> {
+    slice: &'a [T],
+    size: usize,
+    step: usize,
+    pos: usize,
+    allow_partial: bool,
+}
+
+impl<'a, T> Iterator for StridedWindows<'a, T> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<&'a [T]> {
+        let len = self.slice.len();
+        if self.pos >= len {
+            return None;
+        }
+        let end = self.pos + self.size;
+        if end > len && !self.allow_partial {
+            self.pos = len;
+            return None;
+        }
+        let window = &self.slice[self.pos..end.min(len)];
+        self.pos = if end >= len { len } else { self.pos + self.step };
+        Some(window)
+    }
+}
+
+/// Extensions for VecDeque
+pub trait DequeExt<T> {
+    /// Push to the back, evicting from the front until the length is at most `cap`
+    /// Returns the evicted elements, oldest first
+    fn push_back_bounded(&mut self, item: T, cap: usize) -> Vec<T>;
+
+    /// Rotate so the first element matching the predicate is at the front
+    /// Returns false and leaves the deque untouched if nothing matches
+    fn rotate_to<F>(&mut self, predicate: F) -> bool
+    where
+        F: FnMut(&T) -> bool;
+
+    /// Make the storage contiguous and return it as a single slice
+    fn as_contiguous_slice(&mut self) -> &[T];
+}
+
+impl<T> DequeExt<T> for VecDeque<T> {
+    fn push_back_bounded(&mut self, item: T, cap: usize) -> Vec<T> {
+        self.push_back(item);
+        let excess = self.len().saturating_sub(cap);
+        self.drain(..excess).collect()
+    }
+
+    fn rotate_to<F>(&mut self, predicate: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        match self.iter().position(predicate) {
+            Some(index) => {
+                self.rotate_left(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn as_contiguous_slice(&mut self) -> &[T] {
+        self.make_contiguous()
+    }
+}
+
+/// Extensions for HashMap
+pub trait HashMapExt<K, V> {
+    /// Get the value for `key`, inserting the result of a fallible `f` if missing
+    /// If `f` fails, the error is returned and nothing is inserted
+    fn try_get_or_insert_with<E, F>(&mut self, key: K, f: F) -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>;
+
+    /// Move every value through `f`, keeping `Some(new)` and removing the entry on `None`
+    /// Every kept entry is rehashed on reinsertion, and a panic in `f` drops the unvisited entries
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rs_mytools::HashMapExt;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut stock = HashMap::from([("apples", vec![1, 2]), ("pears", vec![])]);
+    /// stock.retain_map_values(|_, mut crates| {
+    ///     crates.push(0);
+    ///     (crates.len() > 1).then_some(crates)
+    /// });
+    /// assert_eq!(stock, HashMap::from([("apples", vec![1, 2, 0])]));
+    /// ```
+    fn retain_map_values<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, V) -> Option<V>;
+
+    /// Retain entries for which `f` returns `Ok(true)`, stopping at the first error
+    /// Entries visited before the error have already been removed or kept
+    fn try_retain<E, F>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnMut(&K, &mut V) -> Result<bool, E>;
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> HashMapExt<K, V> for HashMap<K, V, S> {
+    fn try_get_or_insert_with<E, F>(&mut self, key: K, f: F) -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?)),
+        }
+    }
+
+    fn retain_map_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, V) -> Option<V>,
+    {
+        // Draining keeps the allocation, so reinsertion never grows the table
+        let entries: Vec<(K, V)> = self.drain().collect();
+        for (key, value) in entries {
+            if let Some(value) = f(&key, value) {
+                self.insert(key, value);
+            }
+        }
+    }
+
+    fn try_retain<E, F>(&mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&K, &mut V) -> Result<bool, E>,
+    {
+        let mut error = None;
+        self.retain(|key, value| {
+            if error.is_some() {
+                return true;
+            }
+            match f(key, value) {
+                Ok(keep) => keep,
+                Err(e) => {
+                    error = Some(e);
+                    true
+                }
+            }
+        });
+        error.map_or(Ok(()), Err)
+    }
 }
 
 /// Extensions for vectors containing Result types
@@ -33,13 +434,17 @@ pub trait ResultVecExt<T, E> {
     /// Converts a Vec<Result<T, E>> into a Result<Vec<T>, E>
     /// Collects all values if all are Ok, or returns the first Err
     fn collect_results(self) -> Result<Vec<T>, E>;
+
+    /// Splits a Vec<Result<T, E>> into the Ok values and the errors
+    /// Unlike `collect_results` every error is kept
+    fn partition_results(self) -> (Vec<T>, Vec<E>);
+
+    /// Converts a Vec<Result<T, E>> into a Result<Vec<T>, Vec<E>>
+    /// Collects all values if all are Ok, or returns every Err
+    fn collect_all_errors(self) -> Result<Vec<T>, Vec<E>>;
 }
 
-impl<T, E> ResultVecExt<T, E> for Vec<Result<T, E>> 
-where 
-    T: std::fmt::Debug,
-    E: std::fmt::Debug,
-{
+impl<T, E> ResultVecExt<T, E> for Vec<Result<T, E>> {
     fn collect_results(self) -> Result<Vec<T>, E> {
         let mut results = Vec::with_capacity(self.len());
         
@@ -52,4 +457,2965 @@ where
         
         Ok(results)
     }
+
+    fn partition_results(self) -> (Vec<T>, Vec<E>) {
+        self.into_iter().partition_results()
+    }
+
+    fn collect_all_errors(self) -> Result<Vec<T>, Vec<E>> {
+        let (values, errors) = self.partition_results();
+        if errors.is_empty() {
+            Ok(values)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Extensions for vectors containing Option types
+pub trait OptionVecExt<T> {
+    /// Converts a Vec<Option<T>> into an Option<Vec<T>>
+    /// Collects all values if all are Some, or returns None
+    fn collect_options(self) -> Option<Vec<T>>;
+
+    /// Keeps the values of the Some elements and drops the Nones
+    fn flatten_somes(self) -> Vec<T>;
+}
+
+impl<T> OptionVecExt<T> for Vec<Option<T>> {
+    fn collect_options(self) -> Option<Vec<T>> {
+        let mut values = Vec::with_capacity(self.len());
+
+        for item in self {
+            values.push(item?);
+        }
+
+        Some(values)
+    }
+
+    fn flatten_somes(self) -> Vec<T> {
+        self.into_iter().flatten().collect()
+    }
+}
+
+/// A map whose entries expire a fixed duration after they were inserted
+///
+/// Expired entries are removed lazily when they are accessed, or all at
+/// once with `purge_expired`. A time-to-live too large to represent, such
+/// as `Duration::MAX`, means the entry never expires
+pub struct TtlCache<K, V> {
+    entries: HashMap<K, (V, Option<Instant>)>,
+    ttl: Duration,
+}
+
+impl<K: Eq + Hash, V> TtlCache<K, V> {
+    /// Create an empty cache where entries live for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// The default time-to-live applied by `insert`
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Insert a value using the default time-to-live
+    /// Returns the previous value if it had not yet expired
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ttl = self.ttl;
+        self.insert_with_ttl(key, value, ttl)
+    }
+
+    /// Insert a value that expires after a custom duration
+    /// Returns the previous value if it had not yet expired
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        let now = Instant::now();
+        self.entries
+            .insert(key, (value, now.checked_add(ttl)))
+            .and_then(|(old, expires)| is_live(expires, now).then_some(old))
+    }
+
+    /// Get a reference to a live value, removing it if it has expired
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.evict_if_expired(key);
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    /// Get a mutable reference to a live value, removing it if it has expired
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.evict_if_expired(key);
+        self.entries.get_mut(key).map(|(value, _)| value)
+    }
+
+    /// Check whether a live value exists for the key
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Time left before the entry for `key` expires, `Duration::MAX` if it never does
+    pub fn remaining<Q>(&self, key: &Q) -> Option<Duration>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = Instant::now();
+        self.entries
+            .get(key)
+            .and_then(|(_, expires)| match *expires {
+                Some(at) => (at > now).then(|| at - now),
+                None => Some(Duration::MAX),
+            })
+    }
+
+    /// Remove an entry, returning its value if it had not yet expired
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = Instant::now();
+        self.entries
+            .remove(key)
+            .and_then(|(value, expires)| is_live(expires, now).then_some(value))
+    }
+
+    /// Remove every expired entry and return how many were dropped
+    pub fn purge_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let before = self.entries.len();
+        self.entries.retain(|_, (_, expires)| is_live(*expires, now));
+        before - self.entries.len()
+    }
+
+    /// Number of stored entries, including expired ones not yet purged
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no entries are stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict_if_expired<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = Instant::now();
+        if matches!(self.entries.get(key), Some((_, expires)) if !is_live(*expires, now)) {
+            self.entries.remove(key);
+        }
+    }
+}
+
+fn is_live(expires: Option<Instant>, now: Instant) -> bool {
+    expires.is_none_or(|at| at > now)
+}
+
+/// A vector that keeps its elements in ascending order
+///
+/// Insertion uses binary search, so lookups are `O(log n)` and the
+/// elements can always be viewed as a sorted slice
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortedVec<T> {
+    items: Vec<T>,
+}
+
+impl<T: Ord> SortedVec<T> {
+    /// Create an empty sorted vector
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Create an empty sorted vector with room for `capacity` elements
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Insert a value at its sorted position and return that index
+    /// Equal elements are placed after any existing equal elements
+    pub fn insert(&mut self, value: T) -> usize {
+        let index = self.items.partition_point(|item| item <= &value);
+        self.items.insert(index, value);
+        index
+    }
+
+    /// Insert a value only if no equal element is present
+    /// Returns `Ok(index)` on insertion or `Err(index)` of the existing element
+    pub fn insert_unique(&mut self, value: T) -> Result<usize, usize> {
+        match self.items.binary_search(&value) {
+            Ok(index) => Err(index),
+            Err(index) => {
+                self.items.insert(index, value);
+                Ok(index)
+            }
+        }
+    }
+
+    /// Returns true if an element equal to `value` is present
+    pub fn contains(&self, value: &T) -> bool {
+        self.items.binary_search(value).is_ok()
+    }
+
+    /// Find the index of an element equal to `value`
+    pub fn find(&self, value: &T) -> Option<usize> {
+        self.items.binary_search(value).ok()
+    }
+
+    /// Get the sorted sub-slice of elements within `range`
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> &[T] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.items.partition_point(|item| item < start),
+            Bound::Excluded(start) => self.items.partition_point(|item| item <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.items.partition_point(|item| item <= end),
+            Bound::Excluded(end) => self.items.partition_point(|item| item < end),
+            Bound::Unbounded => self.items.len(),
+        };
+        &self.items[start..end.max(start)]
+    }
+
+    /// Remove one element equal to `value`, returning it if found
+    pub fn remove(&mut self, value: &T) -> Option<T> {
+        self.find(value).map(|index| self.items.remove(index))
+    }
+
+    /// Remove and return the element at `index`
+    pub fn remove_index(&mut self, index: usize) -> T {
+        self.items.remove(index)
+    }
+
+    /// Remove consecutive equal elements, leaving only unique values
+    pub fn dedup(&mut self) {
+        self.items.dedup();
+    }
+
+    /// Keep only the elements that satisfy the predicate
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.items.retain(f);
+    }
+
+    /// Remove all elements
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// View the elements as a sorted slice
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Consume the sorted vector and return the underlying Vec
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl<T: Ord> Default for SortedVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for SortedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for SortedVec<T> {
+    fn from(mut items: Vec<T>) -> Self {
+        items.sort();
+        Self { items }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T: Ord> Extend<T> for SortedVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.items.extend(iter);
+        self.items.sort();
+    }
+}
+
+impl<T> IntoIterator for SortedVec<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SortedVec<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// A vector that stores up to `N` elements inline and spills to the heap beyond that
+///
+/// Small collections avoid allocating entirely, while larger ones behave
+/// like a regular `Vec`. Once spilled, the storage stays on the heap
+pub struct InlineVec<T, const N: usize> {
+    data: InlineData<T, N>,
+}
+
+enum InlineData<T, const N: usize> {
+    Inline { buf: [MaybeUninit<T>; N], len: usize },
+    Heap(Vec<T>),
+}
+
+impl<T, const N: usize> Drop for InlineData<T, N> {
+    fn drop(&mut self) {
+        if let InlineData::Inline { buf, len } = self {
+            // SAFETY: the first `len` slots are always initialized
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    buf.as_mut_ptr() as *mut T,
+                    *len,
+                ));
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> InlineVec<T, N> {
+    /// Create an empty vector using the inline storage
+    pub fn new() -> Self {
+        Self {
+            data: InlineData::Inline {
+                buf: [const { MaybeUninit::uninit() }; N],
+                len: 0,
+            },
+        }
+    }
+
+    /// Create an empty vector, going straight to the heap if `capacity` exceeds `N`
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= N {
+            Self::new()
+        } else {
+            Self {
+                data: InlineData::Heap(Vec::with_capacity(capacity)),
+            }
+        }
+    }
+
+    /// Number of elements in the vector
+    pub fn len(&self) -> usize {
+        match &self.data {
+            InlineData::Inline { len, .. } => *len,
+            InlineData::Heap(vec) => vec.len(),
+        }
+    }
+
+    /// Returns true if the vector contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of elements the vector can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        match &self.data {
+            InlineData::Inline { .. } => N,
+            InlineData::Heap(vec) => vec.capacity(),
+        }
+    }
+
+    /// Returns true if the elements have moved to the heap
+    pub fn spilled(&self) -> bool {
+        matches!(self.data, InlineData::Heap(_))
+    }
+
+    /// Append an element, spilling to the heap if the inline storage is full
+    pub fn push(&mut self, value: T) {
+        match &mut self.data {
+            InlineData::Inline { buf, len } if *len < N => {
+                buf[*len].write(value);
+                *len += 1;
+            }
+            InlineData::Inline { .. } => {
+                self.spill((N * 2).max(1));
+                if let InlineData::Heap(vec) = &mut self.data {
+                    vec.push(value);
+                }
+            }
+            InlineData::Heap(vec) => vec.push(value),
+        }
+    }
+
+    /// Remove the last element and return it
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.data {
+            InlineData::Inline { buf, len } => {
+                if *len == 0 {
+                    return None;
+                }
+                *len -= 1;
+                // SAFETY: the slot was initialized and is now outside `len`
+                Some(unsafe { buf[*len].assume_init_read() })
+            }
+            InlineData::Heap(vec) => vec.pop(),
+        }
+    }
+
+    /// Insert an element at `index`, shifting later elements to the right
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len();
+        assert!(index <= len, "insertion index {} out of bounds (len {})", index, len);
+        self.push(value);
+        self.as_mut_slice()[index..].rotate_right(1);
+    }
+
+    /// Remove and return the element at `index`, shifting later elements to the left
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "removal index {} out of bounds (len {})", index, len);
+        self.as_mut_slice()[index..].rotate_left(1);
+        self.pop().expect("vector is not empty")
+    }
+
+    /// Remove the element at `index` by swapping in the last element
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "removal index {} out of bounds (len {})", index, len);
+        self.as_mut_slice().swap(index, len - 1);
+        self.pop().expect("vector is not empty")
+    }
+
+    /// Shorten the vector to `len` elements, dropping the rest
+    pub fn truncate(&mut self, len: usize) {
+        match &mut self.data {
+            InlineData::Inline { .. } => {
+                while self.len() > len {
+                    self.pop();
+                }
+            }
+            InlineData::Heap(vec) => vec.truncate(len),
+        }
+    }
+
+    /// Remove all elements
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Keep only the elements that satisfy the predicate, preserving order
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let len = self.len();
+        let mut kept = 0;
+        for i in 0..len {
+            if f(&self.as_slice()[i]) {
+                self.as_mut_slice().swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Make room for at least `additional` more elements
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len() + additional;
+        match &mut self.data {
+            InlineData::Inline { .. } if needed > N => self.spill(needed),
+            InlineData::Inline { .. } => {}
+            InlineData::Heap(vec) => vec.reserve(additional),
+        }
+    }
+
+    /// View the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        match &self.data {
+            // SAFETY: the first `len` slots are always initialized
+            InlineData::Inline { buf, len } => unsafe {
+                std::slice::from_raw_parts(buf.as_ptr() as *const T, *len)
+            },
+            InlineData::Heap(vec) => vec,
+        }
+    }
+
+    /// View the elements as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.data {
+            // SAFETY: the first `len` slots are always initialized
+            InlineData::Inline { buf, len } => unsafe {
+                std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut T, *len)
+            },
+            InlineData::Heap(vec) => vec,
+        }
+    }
+
+    /// Convert into a regular Vec, allocating only if the elements are inline
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.spill(0);
+        match &mut self.data {
+            InlineData::Heap(vec) => mem::take(vec),
+            InlineData::Inline { .. } => unreachable!("storage was just spilled"),
+        }
+    }
+
+    // Move inline elements into a heap Vec with at least `capacity` room
+    fn spill(&mut self, capacity: usize) {
+        if let InlineData::Inline { buf, len } = &mut self.data {
+            let mut vec = Vec::with_capacity(capacity.max(*len));
+            for slot in &buf[..*len] {
+                // SAFETY: each initialized slot is read exactly once and
+                // `len` is reset below so it is not dropped again
+                vec.push(unsafe { slot.assume_init_read() });
+            }
+            *len = 0;
+            self.data = InlineData::Heap(vec);
+        }
+    }
+}
+
+impl<T, const N: usize> Default for InlineVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for InlineVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for InlineVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for InlineVec<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for InlineVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for InlineVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for InlineVec<T, N> {}
+
+impl<T, const N: usize> From<Vec<T>> for InlineVec<T, N> {
+    fn from(vec: Vec<T>) -> Self {
+        Self {
+            data: InlineData::Heap(vec),
+        }
+    }
+}
+
+impl<T, const N: usize> Extend<T> for InlineVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for InlineVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a InlineVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut InlineVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for InlineVec<T, N> {
+    type Item = T;
+    type IntoIter = InlineIntoIter<T, N>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let inner = match &mut self.data {
+            InlineData::Inline { buf, len } => {
+                let end = mem::replace(len, 0);
+                let buf = mem::replace(buf, [const { MaybeUninit::uninit() }; N]);
+                InlineIterData::Inline { buf, start: 0, end }
+            }
+            InlineData::Heap(vec) => InlineIterData::Heap(mem::take(vec).into_iter()),
+        };
+        InlineIntoIter { inner }
+    }
+}
+
+/// Owning iterator over the elements of an `InlineVec`
+pub struct InlineIntoIter<T, const N: usize> {
+    inner: InlineIterData<T, N>,
+}
+
+enum InlineIterData<T, const N: usize> {
+    Inline {
+        buf: [MaybeUninit<T>; N],
+        start: usize,
+        end: usize,
+    },
+    Heap(std::vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Drop for InlineIterData<T, N> {
+    fn drop(&mut self) {
+        if let InlineIterData::Inline { buf, start, end } = self {
+            // SAFETY: slots in `start..end` are initialized and not yet yielded
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    buf.as_mut_ptr().add(*start) as *mut T,
+                    *end - *start,
+                ));
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Iterator for InlineIntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match &mut self.inner {
+            InlineIterData::Inline { buf, start, end } => {
+                if start == end {
+                    return None;
+                }
+                *start += 1;
+                // SAFETY: the slot is initialized and now outside `start..end`
+                Some(unsafe { buf[*start - 1].assume_init_read() })
+            }
+            InlineIterData::Heap(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = match &self.inner {
+            InlineIterData::Inline { start, end, .. } => end - start,
+            InlineIterData::Heap(iter) => iter.len(),
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for InlineIntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        match &mut self.inner {
+            InlineIterData::Inline { buf, start, end } => {
+                if start == end {
+                    return None;
+                }
+                *end -= 1;
+                // SAFETY: the slot is initialized and now outside `start..end`
+                Some(unsafe { buf[*end].assume_init_read() })
+            }
+            InlineIterData::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for InlineIntoIter<T, N> {}
+
+/// A HashMap that creates `V::default()` for missing keys on mutable access
+///
+/// Reading a missing key through `Index` yields a shared default value
+/// without inserting anything
+#[derive(Clone)]
+pub struct DefaultMap<K, V> {
+    map: HashMap<K, V>,
+    default: V,
+}
+
+impl<K: Eq + Hash, V: Default> DefaultMap<K, V> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            default: V::default(),
+        }
+    }
+
+    /// Get a mutable reference to the value, inserting `V::default()` if missing
+    pub fn get_mut(&mut self, key: K) -> &mut V {
+        self.map.entry(key).or_default()
+    }
+
+    /// Get a reference to the value without inserting
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Insert a value, returning the previous one if present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    /// Remove a key, returning its value if present
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.remove(key)
+    }
+
+    /// Returns true if the key has been inserted
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if no entries are stored
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterate over the stored key-value pairs
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    /// Iterate over the stored keys
+    pub fn keys(&self) -> std::collections::hash_map::Keys<'_, K, V> {
+        self.map.keys()
+    }
+
+    /// Iterate over the stored values
+    pub fn values(&self) -> std::collections::hash_map::Values<'_, K, V> {
+        self.map.values()
+    }
+
+    /// Consume the map and return the underlying HashMap
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Eq + Hash, V: Default> Default for DefaultMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for DefaultMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.map.iter()).finish()
+    }
+}
+
+impl<K: Eq + Hash, V: Default> Index<K> for DefaultMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: K) -> &V {
+        self.map.get(&key).unwrap_or(&self.default)
+    }
+}
+
+impl<K: Eq + Hash, V: Default> IndexMut<K> for DefaultMap<K, V> {
+    fn index_mut(&mut self, key: K) -> &mut V {
+        self.get_mut(key)
+    }
+}
+
+impl<K: Eq + Hash, V: Default> From<HashMap<K, V>> for DefaultMap<K, V> {
+    fn from(map: HashMap<K, V>) -> Self {
+        Self {
+            map,
+            default: V::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Default> FromIterator<(K, V)> for DefaultMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<HashMap<_, _>>())
+    }
+}
+
+impl<K: Eq + Hash, V: Default> Extend<(K, V)> for DefaultMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.map.extend(iter);
+    }
+}
+
+impl<K, V> IntoIterator for DefaultMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::collections::hash_map::IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a DefaultMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = std::collections::hash_map::Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+/// A prefix tree mapping string keys to values
+///
+/// Children are kept in sorted order, so iteration yields keys
+/// lexicographically. Use `Trie<()>` as a plain set of words
+#[derive(Debug, Clone)]
+pub struct Trie<V = ()> {
+    root: TrieNode<V>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct TrieNode<V> {
+    value: Option<V>,
+    children: BTreeMap<char, TrieNode<V>>,
+}
+
+impl<V> TrieNode<V> {
+    fn new() -> Self {
+        Self {
+            value: None,
+            children: BTreeMap::new(),
+        }
+    }
+
+    fn remove(&mut self, mut chars: std::str::Chars<'_>) -> Option<V> {
+        match chars.next() {
+            None => self.value.take(),
+            Some(c) => {
+                let child = self.children.get_mut(&c)?;
+                let removed = child.remove(chars);
+                if child.value.is_none() && child.children.is_empty() {
+                    self.children.remove(&c);
+                }
+                removed
+            }
+        }
+    }
+}
+
+impl<V> Trie<V> {
+    /// Create an empty trie
+    pub fn new() -> Self {
+        Self {
+            root: TrieNode::new(),
+            len: 0,
+        }
+    }
+
+    /// Insert a key, returning the previous value if the key was present
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        let mut node = &mut self.root;
+        for c in key.chars() {
+            node = node.children.entry(c).or_insert_with(TrieNode::new);
+        }
+        let previous = node.value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Get the value stored for an exact key
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.node(key)?.value.as_ref()
+    }
+
+    /// Get a mutable reference to the value stored for an exact key
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        for c in key.chars() {
+            node = node.children.get_mut(&c)?;
+        }
+        node.value.as_mut()
+    }
+
+    /// Returns true if the exact key is present
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns true if any stored key starts with `prefix`
+    pub fn has_prefix(&self, prefix: &str) -> bool {
+        self.node(prefix).is_some()
+    }
+
+    /// Remove a key, returning its value if it was present
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let removed = self.root.remove(key.chars());
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Find the longest stored key that is a prefix of `input`
+    pub fn longest_prefix_match<'k>(&self, input: &'k str) -> Option<(&'k str, &V)> {
+        let mut node = &self.root;
+        let mut best = node.value.as_ref().map(|value| (0, value));
+        for (i, c) in input.char_indices() {
+            match node.children.get(&c) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(value) = &node.value {
+                best = Some((i + c.len_utf8(), value));
+            }
+        }
+        best.map(|(end, value)| (&input[..end], value))
+    }
+
+    /// Iterate in sorted order over all keys starting with `prefix`
+    pub fn iter_prefix(&self, prefix: &str) -> TriePrefixIter<'_, V> {
+        let stack = match self.node(prefix) {
+            Some(node) => vec![(prefix.to_string(), node)],
+            None => Vec::new(),
+        };
+        TriePrefixIter { stack }
+    }
+
+    /// Iterate in sorted order over all keys
+    pub fn iter(&self) -> TriePrefixIter<'_, V> {
+        self.iter_prefix("")
+    }
+
+    /// Number of stored keys
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no keys are stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all keys
+    pub fn clear(&mut self) {
+        self.root = TrieNode::new();
+        self.len = 0;
+    }
+
+    fn node(&self, prefix: &str) -> Option<&TrieNode<V>> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            node = node.children.get(&c)?;
+        }
+        Some(node)
+    }
+}
+
+impl<V> Default for Trie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, V> FromIterator<(&'a str, V)> for Trie<V> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, V)>>(iter: I) -> Self {
+        let mut trie = Self::new();
+        for (key, value) in iter {
+            trie.insert(key, value);
+        }
+        trie
+    }
+}
+
+/// Iterator over the keys and values of a `Trie` below a prefix
+pub struct TriePrefixIter<'a, V> {
+    stack: Vec<(String, &'a TrieNode<V>)>,
+}
+
+impl<'a, V> Iterator for TriePrefixIter<'a, V> {
+    type Item = (String, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, node)) = self.stack.pop() {
+            for (c, child) in node.children.iter().rev() {
+                let mut child_key = key.clone();
+                child_key.push(*c);
+                self.stack.push((child_key, child));
+            }
+            if let Some(value) = &node.value {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+/// A map from half-open ranges of keys to values
+///
+/// By default ranges never overlap: inserting a range overwrites whatever
+/// it covers, splitting partially covered ranges. A map created with
+/// `with_overlaps` keeps every inserted range instead
+#[derive(Debug, Clone)]
+pub struct IntervalMap<K, V> {
+    entries: Vec<(Range<K>, V)>,
+    allow_overlaps: bool,
+}
+
+impl<K: Ord + Clone, V> IntervalMap<K, V> {
+    /// Create an empty map where new ranges replace the parts they overlap
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            allow_overlaps: false,
+        }
+    }
+
+    /// Create an empty map that stores overlapping ranges side by side
+    pub fn with_overlaps() -> Self {
+        Self {
+            entries: Vec::new(),
+            allow_overlaps: true,
+        }
+    }
+
+    /// Returns true if this map keeps overlapping ranges
+    pub fn allows_overlaps(&self) -> bool {
+        self.allow_overlaps
+    }
+
+    /// Insert a value for `range`; empty ranges are ignored
+    ///
+    /// Without overlaps, any existing ranges covered by `range` are removed
+    /// and partially covered ones are trimmed or split in two. Splitting a
+    /// range gives both halves its value, which is why `V: Clone` is required
+    pub fn insert(&mut self, range: Range<K>, value: V)
+    where
+        V: Clone,
+    {
+        if range.start >= range.end {
+            return;
+        }
+        if !self.allow_overlaps {
+            self.cut(&range);
+        }
+        let index = self
+            .entries
+            .partition_point(|(r, _)| r.start <= range.start);
+        self.entries.insert(index, (range, value));
+    }
+
+    /// Remove everything inside `range`, trimming or splitting ranges that extend past it
+    pub fn remove(&mut self, range: Range<K>)
+    where
+        V: Clone,
+    {
+        if range.start < range.end {
+            self.cut(&range);
+        }
+    }
+
+    /// Get the value of the first range containing `point`
+    pub fn get(&self, point: &K) -> Option<&V> {
+        self.get_entry(point).map(|(_, value)| value)
+    }
+
+    /// Get the first range containing `point` together with its value
+    pub fn get_entry(&self, point: &K) -> Option<(&Range<K>, &V)> {
+        self.get_all(point).next()
+    }
+
+    /// Iterate over every range containing `point`, ordered by start
+    ///
+    /// Without overlaps this is a binary search. With overlaps any earlier
+    /// range may still contain `point`, so every range starting at or before
+    /// it is scanned, which is O(n)
+    pub fn get_all(&self, point: &K) -> impl Iterator<Item = (&Range<K>, &V)> {
+        let end = self.entries.partition_point(|(r, _)| r.start <= *point);
+        let start = if self.allow_overlaps { 0 } else { end.saturating_sub(1) };
+        let point = point.clone();
+        self.entries[start..end]
+            .iter()
+            .filter(move |(r, _)| r.end > point)
+            .map(|(r, v)| (r, v))
+    }
+
+    /// Iterate over every stored range that intersects `range`, ordered by start
+    ///
+    /// An empty `range` intersects nothing. Every range starting before the
+    /// end of `range` is scanned
+    pub fn overlaps(&self, range: &Range<K>) -> impl Iterator<Item = (&Range<K>, &V)> {
+        let end = if range.start < range.end {
+            self.entries.partition_point(|(r, _)| r.start < range.end)
+        } else {
+            0
+        };
+        let start = range.start.clone();
+        self.entries[..end]
+            .iter()
+            .filter(move |(r, _)| r.end > start)
+            .map(|(r, v)| (r, v))
+    }
+
+    /// Iterate over all ranges and values, ordered by start
+    pub fn iter(&self) -> impl Iterator<Item = (&Range<K>, &V)> {
+        self.entries.iter().map(|(r, v)| (r, v))
+    }
+
+    /// Number of stored ranges
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no ranges are stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all ranges
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Remove the part of every stored range that lies inside `range`
+    fn cut(&mut self, range: &Range<K>)
+    where
+        V: Clone,
+    {
+        let mut kept = Vec::with_capacity(self.entries.len() + 1);
+        for (r, value) in self.entries.drain(..) {
+            if r.end <= range.start || r.start >= range.end {
+                kept.push((r, value));
+                continue;
+            }
+            let keep_left = r.start < range.start;
+            let keep_right = r.end > range.end;
+            match (keep_left, keep_right) {
+                (true, true) => {
+                    kept.push((r.start..range.start.clone(), value.clone()));
+                    kept.push((range.end.clone()..r.end, value));
+                }
+                (true, false) => kept.push((r.start..range.start.clone(), value)),
+                (false, true) => kept.push((range.end.clone()..r.end, value)),
+                (false, false) => {}
+            }
+        }
+        kept.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start));
+        self.entries = kept;
+    }
+}
+
+impl<K: Ord + Clone, V> Default for IntervalMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A growable set of small integers stored as a packed bit vector
+#[derive(Clone, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    const WORD_BITS: usize = 64;
+
+    /// Create an empty bit set
+    pub fn new() -> Self {
+        Self { words: Vec::new() }
+    }
+
+    /// Create an empty bit set with room for indices below `bits`
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            words: vec![0; bits.div_ceil(Self::WORD_BITS)],
+        }
+    }
+
+    /// Set the bit at `index`, growing the set if needed
+    /// Returns true if the bit was previously clear
+    pub fn set(&mut self, index: usize) -> bool {
+        let (word, mask) = Self::locate(index);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let was_clear = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        was_clear
+    }
+
+    /// Clear the bit at `index`
+    /// Returns true if the bit was previously set
+    pub fn clear(&mut self, index: usize) -> bool {
+        let (word, mask) = Self::locate(index);
+        match self.words.get_mut(word) {
+            Some(bits) => {
+                let was_set = *bits & mask != 0;
+                *bits &= !mask;
+                was_set
+            }
+            None => false,
+        }
+    }
+
+    /// Flip the bit at `index` and return its new state
+    pub fn toggle(&mut self, index: usize) -> bool {
+        if self.test(index) {
+            self.clear(index);
+            false
+        } else {
+            self.set(index);
+            true
+        }
+    }
+
+    /// Returns true if the bit at `index` is set
+    pub fn test(&self, index: usize) -> bool {
+        let (word, mask) = Self::locate(index);
+        self.words.get(word).is_some_and(|bits| bits & mask != 0)
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    /// Returns true if no bits are set
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&bits| bits == 0)
+    }
+
+    /// Number of bits that can be tested without growing
+    pub fn capacity(&self) -> usize {
+        self.words.len() * Self::WORD_BITS
+    }
+
+    /// Clear every bit
+    pub fn clear_all(&mut self) {
+        self.words.clear();
+    }
+
+    /// Set every bit that is set in `other`
+    pub fn union_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (bits, other) in self.words.iter_mut().zip(&other.words) {
+            *bits |= other;
+        }
+    }
+
+    /// Clear every bit that is not set in `other`
+    pub fn intersect_with(&mut self, other: &BitSet) {
+        self.words.truncate(other.words.len());
+        for (bits, other) in self.words.iter_mut().zip(&other.words) {
+            *bits &= other;
+        }
+    }
+
+    /// Flip every bit that is set in `other`
+    pub fn symmetric_difference_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (bits, other) in self.words.iter_mut().zip(&other.words) {
+            *bits ^= other;
+        }
+    }
+
+    /// Clear every bit that is set in `other`
+    pub fn difference_with(&mut self, other: &BitSet) {
+        for (bits, other) in self.words.iter_mut().zip(&other.words) {
+            *bits &= !other;
+        }
+    }
+
+    /// Returns true if every bit set here is also set in `other`
+    pub fn is_subset(&self, other: &BitSet) -> bool {
+        self.words.iter().enumerate().all(|(i, bits)| {
+            bits & !other.words.get(i).copied().unwrap_or(0) == 0
+        })
+    }
+
+    /// Iterate over the indices of set bits in ascending order
+    pub fn iter(&self) -> BitSetIter<'_> {
+        BitSetIter {
+            words: &self.words,
+            word: 0,
+            current: self.words.first().copied().unwrap_or(0),
+        }
+    }
+
+    fn locate(index: usize) -> (usize, u64) {
+        (index / Self::WORD_BITS, 1 << (index % Self::WORD_BITS))
+    }
+
+    // Word slice without trailing zero words, so equality ignores capacity
+    fn trimmed(&self) -> &[u64] {
+        let len = self.words.iter().rposition(|&bits| bits != 0).map_or(0, |i| i + 1);
+        &self.words[..len]
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+
+impl Eq for BitSet {}
+
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trimmed().hash(state);
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl BitAnd for &BitSet {
+    type Output = BitSet;
+
+    fn bitand(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.intersect_with(other);
+        result
+    }
+}
+
+impl BitOr for &BitSet {
+    type Output = BitSet;
+
+    fn bitor(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.union_with(other);
+        result
+    }
+}
+
+impl BitXor for &BitSet {
+    type Output = BitSet;
+
+    fn bitxor(self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        result.symmetric_difference_with(other);
+        result
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<usize> for BitSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.set(index);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a BitSet {
+    type Item = usize;
+    type IntoIter = BitSetIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the indices of set bits in a `BitSet`
+pub struct BitSetIter<'a> {
+    words: &'a [u64],
+    word: usize,
+    current: u64,
+}
+
+impl Iterator for BitSetIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.word += 1;
+            self.current = *self.words.get(self.word)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.word * BitSet::WORD_BITS + bit)
+    }
+}
+
+/// A map that remembers insertion order while keeping O(1) lookup
+///
+/// Updating an existing key keeps its original position
+#[derive(Clone)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+    indices: HashMap<K, usize>,
+}
+
+impl<K: Eq + Hash + Clone, V> OrderedMap<K, V> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Create an empty map with room for `capacity` entries
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Insert a value, returning the previous one if the key was present
+    /// New keys are appended; existing keys keep their position
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.indices.get(&key) {
+            Some(&index) => Some(mem::replace(&mut self.entries[index].1, value)),
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Get a reference to the value for `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.indices.get(key).map(|&index| &self.entries[index].1)
+    }
+
+    /// Get a mutable reference to the value for `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = *self.indices.get(key)?;
+        Some(&mut self.entries[index].1)
+    }
+
+    /// Get the entry at position `index` in insertion order
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.entries.get(index).map(|(k, v)| (k, v))
+    }
+
+    /// Position of `key` in insertion order
+    pub fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.indices.get(key).copied()
+    }
+
+    /// Returns true if the key is present
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.indices.contains_key(key)
+    }
+
+    /// Remove a key, shifting later entries down to preserve order (O(n))
+    pub fn shift_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for (k, _) in &self.entries[index..] {
+            if let Some(i) = self.indices.get_mut::<K>(k) {
+                *i -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// Remove a key by moving the last entry into its place (O(1), changes order)
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.swap_remove(index);
+        if let Some((moved, _)) = self.entries.get(index) {
+            if let Some(i) = self.indices.get_mut::<K>(moved) {
+                *i = index;
+            }
+        }
+        Some(value)
+    }
+
+    /// Remove and return the most recently inserted entry
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.entries.pop()?;
+        self.indices.remove(&key);
+        Some((key, value))
+    }
+
+    /// First entry in insertion order
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.get_index(0)
+    }
+
+    /// Last entry in insertion order
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.entries.last().map(|(k, v)| (k, v))
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    /// Iterate over entries in insertion order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Iterate over entries in insertion order with mutable values
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&K, &mut V)> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    /// Iterate over keys in insertion order
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    /// Iterate over values in insertion order
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    /// Iterate over mutable values in insertion order
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+impl<K, V, Q> Index<&Q> for OrderedMap<K, V>
+where
+    K: Eq + Hash + Clone + Borrow<Q>,
+    Q: Eq + Hash + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in OrderedMap")
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Extend<(K, V)> for OrderedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// A priority queue keyed by `K` whose priorities can be changed after insertion
+///
+/// Both the minimum and maximum can be popped in `O(log n)`. Among equal
+/// priorities, `pop_min` returns the earliest pushed key and `pop_max` the latest
+#[derive(Debug, Clone)]
+pub struct KeyedPriorityQueue<K, P> {
+    order: BTreeMap<(P, u64), K>,
+    keys: HashMap<K, (P, u64)>,
+    next_seq: u64,
+}
+
+impl<K: Eq + Hash + Clone, P: Ord + Clone> KeyedPriorityQueue<K, P> {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self {
+            order: BTreeMap::new(),
+            keys: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Insert a key with a priority, replacing the priority if the key is present
+    /// Returns the previous priority
+    pub fn push(&mut self, key: K, priority: P) -> Option<P> {
+        let previous = self.remove(&key);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.order.insert((priority.clone(), seq), key.clone());
+        self.keys.insert(key, (priority, seq));
+        previous
+    }
+
+    /// Change the priority of an existing key, returning the old priority
+    /// Returns None and does nothing if the key is not queued
+    pub fn change_priority<Q>(&mut self, key: &Q, priority: P) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (old, seq) = self.keys.get_mut(key)?;
+        let key = self.order.remove(&(old.clone(), *seq))?;
+        let previous = mem::replace(old, priority.clone());
+        self.order.insert((priority, *seq), key);
+        Some(previous)
+    }
+
+    /// Remove and return the key with the smallest priority
+    pub fn pop_min(&mut self) -> Option<(K, P)> {
+        let ((priority, _), key) = self.order.pop_first()?;
+        self.keys.remove(&key);
+        Some((key, priority))
+    }
+
+    /// Remove and return the key with the largest priority
+    pub fn pop_max(&mut self) -> Option<(K, P)> {
+        let ((priority, _), key) = self.order.pop_last()?;
+        self.keys.remove(&key);
+        Some((key, priority))
+    }
+
+    /// Peek at the key with the smallest priority
+    pub fn peek_min(&self) -> Option<(&K, &P)> {
+        self.order
+            .first_key_value()
+            .map(|((priority, _), key)| (key, priority))
+    }
+
+    /// Peek at the key with the largest priority
+    pub fn peek_max(&self) -> Option<(&K, &P)> {
+        self.order
+            .last_key_value()
+            .map(|((priority, _), key)| (key, priority))
+    }
+
+    /// Remove a key, returning its priority
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (priority, seq) = self.keys.remove(key)?;
+        self.order.remove(&(priority.clone(), seq));
+        Some(priority)
+    }
+
+    /// Current priority of a key
+    pub fn priority<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.keys.get(key).map(|(priority, _)| priority)
+    }
+
+    /// Returns true if the key is queued
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.keys.contains_key(key)
+    }
+
+    /// Number of queued keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Remove all keys
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.keys.clear();
+    }
+}
+
+impl<K: Eq + Hash + Clone, P: Ord + Clone> Default for KeyedPriorityQueue<K, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone, P: Ord + Clone> FromIterator<(K, P)> for KeyedPriorityQueue<K, P> {
+    fn from_iter<I: IntoIterator<Item = (K, P)>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        for (key, priority) in iter {
+            queue.push(key, priority);
+        }
+        queue
+    }
+}
+
+/// Disjoint-set forest over the elements `0..len`
+///
+/// Uses path compression and union by rank, so operations run in
+/// near-constant amortized time
+#[derive(Debug, Clone, Default)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    sets: usize,
+}
+
+impl UnionFind {
+    /// Create `n` singleton sets
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            sets: n,
+        }
+    }
+
+    /// Add a new singleton set and return its element
+    pub fn make_set(&mut self) -> usize {
+        let element = self.parent.len();
+        self.parent.push(element);
+        self.rank.push(0);
+        self.sets += 1;
+        element
+    }
+
+    /// Find the representative of the set containing `x`
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = x;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    /// Merge the sets containing `a` and `b`
+    /// Returns false if they were already in the same set
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (child, root) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[child] = root;
+        if self.rank[child] == self.rank[root] {
+            self.rank[root] += 1;
+        }
+        self.sets -= 1;
+        true
+    }
+
+    /// Returns true if `a` and `b` are in the same set
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Returns true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    /// Collect the elements of every set
+    /// Groups are ordered by their smallest element and each group is sorted
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut group_of = HashMap::new();
+        let mut groups: Vec<Vec<usize>> = Vec::with_capacity(self.sets);
+        for element in 0..self.parent.len() {
+            let root = self.find(element);
+            let index = *group_of.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[index].push(element);
+        }
+        groups
+    }
+}
+
+/// A single step in the edit script produced by `diff` or `diff_ref`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffOp<T> {
+    /// The element is present in both sequences
+    Equal(T),
+    /// The element only exists in the new sequence
+    Insert(T),
+    /// The element only exists in the old sequence
+    Delete(T),
+}
+
+impl<T> DiffOp<T> {
+    /// The element this operation refers to
+    pub fn value(&self) -> &T {
+        match self {
+            DiffOp::Equal(value) | DiffOp::Insert(value) | DiffOp::Delete(value) => value,
+        }
+    }
+
+    /// Returns true for `Equal` operations
+    pub fn is_equal(&self) -> bool {
+        matches!(self, DiffOp::Equal(_))
+    }
+
+    /// Apply a function to the element, keeping the operation kind
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> DiffOp<U> {
+        match self {
+            DiffOp::Equal(value) => DiffOp::Equal(f(value)),
+            DiffOp::Insert(value) => DiffOp::Insert(f(value)),
+            DiffOp::Delete(value) => DiffOp::Delete(f(value)),
+        }
+    }
+}
+
+/// Compute a shortest edit script turning `old` into `new` using Myers' algorithm
+///
+/// Deletions are emitted before insertions when both occur at the same
+/// position. The elements are cloned into the script; use `diff_ref` to
+/// borrow them instead
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::collections::{diff, DiffOp};
+///
+/// assert_eq!(
+///     diff(&["a", "b", "c"], &["a", "c", "d"]),
+///     [
+///         DiffOp::Equal("a"),
+///         DiffOp::Delete("b"),
+///         DiffOp::Equal("c"),
+///         DiffOp::Insert("d"),
+///     ]
+/// );
+/// ```
+pub fn diff<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Vec<DiffOp<T>> {
+    diff_ref(old, new).into_iter().map(|op| op.map(T::clone)).collect()
+}
+
+/// Like `diff`, but the operations borrow the elements from the inputs
+pub fn diff_ref<'a, T: PartialEq>(old: &'a [T], new: &'a [T]) -> Vec<DiffOp<&'a T>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let offset = n + m;
+    let mut v = vec![0isize; 2 * offset as usize + 2];
+    let mut trace = Vec::new();
+
+    // Whether the path on diagonal `k` comes from `k + 1` (an insertion)
+    let from_above = |v: &[isize], k: isize, d: isize| {
+        k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize])
+    };
+
+    'search: for d in 0..=offset {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if from_above(&v, k, d) {
+                v[(k + 1 + offset) as usize]
+            } else {
+                v[(k - 1 + offset) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if from_above(v, k, d) { k + 1 } else { k - 1 };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(DiffOp::Equal(&old[x as usize]));
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                ops.push(DiffOp::Insert(&new[y as usize]));
+            } else {
+                x -= 1;
+                ops.push(DiffOp::Delete(&old[x as usize]));
+            }
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+/// Keeps only the `n` best items ever pushed, by largest or smallest
+///
+/// Backed by a bounded binary heap, so pushing is `O(log n)` and memory
+/// stays proportional to `n`. To rank by a key, push `(key, item)` tuples
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::collections::TopN;
+///
+/// let mut top = TopN::largest(3);
+/// top.extend([5, 1, 9, 3, 7]);
+/// assert_eq!(top.push(2), Some(2));
+/// assert_eq!(top.push(8), Some(5));
+/// assert_eq!(top.into_sorted_vec(), [9, 8, 7]);
+///
+/// let mut fastest = TopN::smallest(2);
+/// fastest.extend([(12, "b"), (9, "a"), (15, "c")]);
+/// assert_eq!(fastest.into_sorted_vec(), [(9, "a"), (12, "b")]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TopN<T> {
+    heap: BinaryHeap<Ranked<T>>,
+    n: usize,
+    largest: bool,
+}
+
+// Orders items so the heap's top is the worst retained item
+#[derive(Debug, Clone)]
+struct Ranked<T> {
+    item: T,
+    largest: bool,
+}
+
+impl<T: Ord> PartialEq for Ranked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item
+    }
+}
+
+impl<T: Ord> Eq for Ranked<T> {}
+
+impl<T: Ord> PartialOrd for Ranked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Ranked<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.largest {
+            other.item.cmp(&self.item)
+        } else {
+            self.item.cmp(&other.item)
+        }
+    }
+}
+
+impl<T: Ord> TopN<T> {
+    /// Keep the `n` largest items
+    pub fn largest(n: usize) -> Self {
+        Self {
+            heap: BinaryHeap::with_capacity(n),
+            n,
+            largest: true,
+        }
+    }
+
+    /// Keep the `n` smallest items
+    pub fn smallest(n: usize) -> Self {
+        Self {
+            heap: BinaryHeap::with_capacity(n),
+            n,
+            largest: false,
+        }
+    }
+
+    /// Offer an item, returning whichever item was dropped (if any)
+    /// That is either the pushed item itself or a previously retained one
+    pub fn push(&mut self, item: T) -> Option<T> {
+        let ranked = Ranked {
+            item,
+            largest: self.largest,
+        };
+        if self.heap.len() < self.n {
+            self.heap.push(ranked);
+            return None;
+        }
+        match self.heap.peek() {
+            Some(worst) if ranked < *worst => {
+                let mut worst = self.heap.peek_mut()?;
+                Some(mem::replace(&mut *worst, ranked).item)
+            }
+            _ => Some(ranked.item),
+        }
+    }
+
+    /// The worst item still retained, which a new item must beat to get in
+    pub fn threshold(&self) -> Option<&T> {
+        self.heap.peek().map(|ranked| &ranked.item)
+    }
+
+    /// Maximum number of items retained
+    pub fn capacity(&self) -> usize {
+        self.n
+    }
+
+    /// Number of items currently retained
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Returns true if no items are retained
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Iterate over the retained items in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.heap.iter().map(|ranked| &ranked.item)
+    }
+
+    /// Consume and return the retained items, best first
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ranked| ranked.item)
+            .collect()
+    }
+}
+
+impl<T: Ord> Extend<T> for TopN<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+/// Floating point types that can be given a total order
+///
+/// NaN sorts above every other value and all NaNs compare equal;
+/// `-0.0` and `0.0` compare equal
+pub trait TotalOrder: Copy {
+    /// Compare two values using the total order
+    fn total_order(&self, other: &Self) -> Ordering;
+
+    /// Bit pattern that is identical for values considered equal
+    fn canonical_bits(&self) -> u64;
+}
+
+macro_rules! impl_total_order {
+    ($($float:ty),+) => {
+        $(
+            impl TotalOrder for $float {
+                fn total_order(&self, other: &Self) -> Ordering {
+                    match (self.is_nan(), other.is_nan()) {
+                        (true, true) => Ordering::Equal,
+                        (true, false) => Ordering::Greater,
+                        (false, true) => Ordering::Less,
+                        (false, false) => self.partial_cmp(other).unwrap_or(Ordering::Equal),
+                    }
+                }
+
+                fn canonical_bits(&self) -> u64 {
+                    if self.is_nan() {
+                        <$float>::NAN.to_bits() as u64
+                    } else if *self == 0.0 {
+                        0
+                    } else {
+                        self.to_bits() as u64
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_total_order!(f32, f64);
+
+/// Wrapper giving floats `Ord`, `Eq` and `Hash` so they can be used as
+/// sort keys, heap priorities and map keys
+///
+/// See `TotalOrder` for how NaN and signed zeros are treated
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Total<T>(pub T);
+
+impl<T> Total<T> {
+    /// Unwrap the inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: TotalOrder> PartialEq for Total<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_order(&other.0) == Ordering::Equal
+    }
+}
+
+impl<T: TotalOrder> Eq for Total<T> {}
+
+impl<T: TotalOrder> PartialOrd for Total<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: TotalOrder> Ord for Total<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_order(&other.0)
+    }
+}
+
+impl<T: TotalOrder> Hash for Total<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.canonical_bits().hash(state);
+    }
+}
+
+impl<T> Deref for Total<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Total<T> {
+    fn from(value: T) -> Self {
+        Total(value)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Total<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Order `items` so that every item comes after the dependencies returned by `deps`
+///
+/// The result is deterministic and stays as close to the input order as the
+/// dependencies allow. Dependencies that are not in `items` are included too.
+/// Fails with the offending cycle if the dependencies are circular
+pub fn resolve_order<T, I, F>(
+    items: impl IntoIterator<Item = T>,
+    mut deps: F,
+) -> Result<Vec<T>, CycleError<T>>
+where
+    T: Eq + Hash + Clone,
+    I: IntoIterator<Item = T>,
+    F: FnMut(&T) -> I,
+{
+    let items: Vec<T> = items.into_iter().collect();
+    let mut graph = DiGraph::new();
+    for item in &items {
+        graph.add_node(item.clone());
+    }
+    for item in &items {
+        for dependency in deps(item) {
+            graph.add_edge(dependency, item.clone());
+        }
+    }
+    graph.topo_sort()
+}
+
+/// An immutable singly linked list with structural sharing
+///
+/// `push_front` and `tail` are `O(1)` and never copy elements, so many
+/// versions of a list can share their common suffix cheaply
+pub struct PList<T> {
+    head: Option<Rc<PNode<T>>>,
+    len: usize,
+}
+
+struct PNode<T> {
+    value: T,
+    next: Option<Rc<PNode<T>>>,
+}
+
+impl<T> PList<T> {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self { head: None, len: 0 }
+    }
+
+    /// Return a new list with `value` in front of this one
+    pub fn push_front(&self, value: T) -> Self {
+        Self {
+            head: Some(Rc::new(PNode {
+                value,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    /// The first element
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    /// The list without its first element, or None if the list is empty
+    pub fn tail(&self) -> Option<Self> {
+        self.head.as_ref().map(|node| Self {
+            head: node.next.clone(),
+            len: self.len - 1,
+        })
+    }
+
+    /// The first element together with the rest of the list
+    pub fn split_first(&self) -> Option<(&T, Self)> {
+        Some((self.head()?, self.tail()?))
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the list has no elements
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// Returns true if both lists share the same first node
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Iterate over the elements from front to back
+    pub fn iter(&self) -> PListIter<'_, T> {
+        PListIter {
+            next: self.head.as_deref(),
+            remaining: self.len,
+        }
+    }
+
+    /// Return a new list with the elements in reverse order
+    pub fn reversed(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.iter()
+            .fold(Self::new(), |list, value| list.push_front(value.clone()))
+    }
+}
+
+impl<T> Clone for PList<T> {
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Default for PList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Drop iteratively so long lists don't overflow the stack
+impl<T> Drop for PList<T> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for PList<T> {}
+
+impl<T> FromIterator<T> for PList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        items
+            .into_iter()
+            .rev()
+            .fold(Self::new(), |list, value| list.push_front(value))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PList<T> {
+    type Item = &'a T;
+    type IntoIter = PListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of a `PList`
+pub struct PListIter<'a, T> {
+    next: Option<&'a PNode<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for PListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.next?;
+        self.next = node.next.as_deref();
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for PListIter<'_, T> {}
+
+/// Stable handle to a value stored in a `SlotMap`
+///
+/// Keys carry a generation, so a key to a removed value never resolves to
+/// whatever later reuses its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    generation: u32,
+    dense: usize,
+    occupied: bool,
+}
+
+/// A generational arena with stable keys and densely packed values
+///
+/// Insertion, lookup and removal are `O(1)`, and iteration walks a
+/// contiguous Vec of values. Removal moves the last value into the gap,
+/// so iteration order is not insertion order
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::collections::SlotMap;
+///
+/// let mut players = SlotMap::new();
+/// let alice = players.insert("alice");
+/// let bob = players.insert("bob");
+///
+/// assert_eq!(players.remove(alice), Some("alice"));
+/// let carol = players.insert("carol");
+///
+/// // The stale key does not resolve to the value that reused its slot
+/// assert_eq!(players.get(alice), None);
+/// assert_eq!(players[carol], "carol");
+/// assert_eq!(players[bob], "bob");
+/// assert_eq!(players.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SlotMap<T> {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    values: Vec<T>,
+    keys: Vec<SlotKey>,
+}
+
+impl<T> SlotMap<T> {
+    /// Create an empty slot map
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            values: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Create an empty slot map with room for `capacity` values
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            values: Vec::with_capacity(capacity),
+            keys: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Store a value and return its key
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        let dense = self.values.len();
+        let key = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.dense = dense;
+                slot.occupied = true;
+                SlotKey {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("SlotMap is full");
+                self.slots.push(Slot {
+                    generation: 0,
+                    dense,
+                    occupied: true,
+                });
+                SlotKey {
+                    index,
+                    generation: 0,
+                }
+            }
+        };
+        self.values.push(value);
+        self.keys.push(key);
+        key
+    }
+
+    /// Get a reference to the value for `key`, or None if it was removed
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        self.dense_index(key).map(|dense| &self.values[dense])
+    }
+
+    /// Get a mutable reference to the value for `key`, or None if it was removed
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        self.dense_index(key).map(|dense| &mut self.values[dense])
+    }
+
+    /// Returns true if `key` still refers to a stored value
+    pub fn contains_key(&self, key: SlotKey) -> bool {
+        self.dense_index(key).is_some()
+    }
+
+    /// Remove the value for `key`, invalidating the key
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let dense = self.dense_index(key)?;
+        let slot = &mut self.slots[key.index as usize];
+        slot.occupied = false;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+
+        let value = self.values.swap_remove(dense);
+        self.keys.swap_remove(dense);
+        if let Some(moved) = self.keys.get(dense) {
+            self.slots[moved.index as usize].dense = dense;
+        }
+        Some(value)
+    }
+
+    /// Keep only the values for which the predicate returns true
+    pub fn retain<F: FnMut(SlotKey, &mut T) -> bool>(&mut self, mut f: F) {
+        let mut dense = 0;
+        while dense < self.values.len() {
+            let key = self.keys[dense];
+            if f(key, &mut self.values[dense]) {
+                dense += 1;
+            } else {
+                self.remove(key);
+            }
+        }
+    }
+
+    /// Number of stored values
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if no values are stored
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Remove every value, invalidating all keys
+    pub fn clear(&mut self) {
+        for key in self.keys.drain(..) {
+            let slot = &mut self.slots[key.index as usize];
+            slot.occupied = false;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(key.index);
+        }
+        self.values.clear();
+    }
+
+    /// Iterate over keys and values in storage order
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.keys.iter().copied().zip(self.values.iter())
+    }
+
+    /// Iterate over keys and mutable values in storage order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlotKey, &mut T)> {
+        self.keys.iter().copied().zip(self.values.iter_mut())
+    }
+
+    /// Iterate over the keys in storage order
+    pub fn keys(&self) -> impl Iterator<Item = SlotKey> + '_ {
+        self.keys.iter().copied()
+    }
+
+    /// The stored values as a contiguous slice
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The stored values as a contiguous mutable slice
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    fn dense_index(&self, key: SlotKey) -> Option<usize> {
+        let slot = self.slots.get(key.index as usize)?;
+        (slot.occupied && slot.generation == key.generation).then_some(slot.dense)
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<SlotKey> for SlotMap<T> {
+    type Output = T;
+
+    fn index(&self, key: SlotKey) -> &T {
+        self.get(key).expect("invalid SlotMap key")
+    }
+}
+
+impl<T> IndexMut<SlotKey> for SlotMap<T> {
+    fn index_mut(&mut self, key: SlotKey) -> &mut T {
+        self.get_mut(key).expect("invalid SlotMap key")
+    }
+}
+
+/// An immutable lookup table with string keys, usable in `const` and `static` items
+///
+/// Entries are sorted by key so lookups are a binary search. Build one with
+/// the `static_map!` macro, which sorts the entries at compile time
+#[derive(Debug, Clone, Copy)]
+pub struct StaticMap<V: 'static> {
+    entries: &'static [(&'static str, V)],
+}
+
+impl<V> StaticMap<V> {
+    /// Wrap entries that are already sorted by key, panicking if they are not
+    pub const fn from_sorted(entries: &'static [(&'static str, V)]) -> Self {
+        let mut i = 1;
+        while i < entries.len() {
+            if !matches!(const_str_cmp(entries[i - 1].0, entries[i].0), Ordering::Less) {
+                panic!("StaticMap keys must be sorted and unique");
+            }
+            i += 1;
+        }
+        Self { entries }
+    }
+
+    /// Look up the value stored for a key
+    pub fn get(&self, key: &str) -> Option<&'static V> {
+        let entries = self.entries;
+        entries
+            .binary_search_by(|(k, _)| (*k).cmp(key))
+            .ok()
+            .map(|index| &entries[index].1)
+    }
+
+    /// Look up the stored key and value for a key
+    pub fn get_key_value(&self, key: &str) -> Option<(&'static str, &'static V)> {
+        let entries = self.entries;
+        entries
+            .binary_search_by(|(k, _)| (*k).cmp(key))
+            .ok()
+            .map(|index| (entries[index].0, &entries[index].1))
+    }
+
+    /// Returns true if the key is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The number of entries
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries in key order
+    pub const fn entries(&self) -> &'static [(&'static str, V)] {
+        self.entries
+    }
+
+    /// Iterate over the keys in order
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> {
+        self.entries.iter().map(|(k, _)| *k)
+    }
+
+    /// Iterate over the values in key order
+    pub fn values(&self) -> impl Iterator<Item = &'static V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+/// Sort `static_map!` entries by key at compile time (used internally)
+#[doc(hidden)]
+pub const fn sort_static_entries<V, const N: usize>(
+    mut entries: [(&'static str, V); N],
+) -> [(&'static str, V); N] {
+    let mut i = 1;
+    while i < N {
+        let mut j = i;
+        while j > 0 {
+            match const_str_cmp(entries[j - 1].0, entries[j].0) {
+                Ordering::Greater => entries.swap(j - 1, j),
+                Ordering::Equal => panic!("duplicate key in static_map!"),
+                Ordering::Less => break,
+            }
+            j -= 1;
+        }
+        i += 1;
+    }
+    entries
+}
+
+const fn const_str_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut i = 0;
+    while i < a.len() && i < b.len() {
+        if a[i] != b[i] {
+            return if a[i] < b[i] { Ordering::Less } else { Ordering::Greater };
+        }
+        i += 1;
+    }
+    if a.len() < b.len() {
+        Ordering::Less
+    } else if a.len() > b.len() {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    // Counts drops through a shared counter, optionally panicking on the nth clone
+    struct Tracked<'a> {
+        id: usize,
+        drops: &'a Cell<usize>,
+        clones_left: &'a Cell<usize>,
+    }
+
+    impl Clone for Tracked<'_> {
+        fn clone(&self) -> Self {
+            let left = self.clones_left.get();
+            assert!(left > 0, "clone budget exhausted");
+            self.clones_left.set(left - 1);
+            Tracked { ..*self }
+        }
+    }
+
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    fn tracked<'a, const N: usize>(
+        count: usize,
+        drops: &'a Cell<usize>,
+        clones_left: &'a Cell<usize>,
+    ) -> InlineVec<Tracked<'a>, N> {
+        (0..count).map(|id| Tracked { id, drops, clones_left }).collect()
+    }
+
+    fn ids(vec: &[Tracked<'_>]) -> Vec<usize> {
+        vec.iter().map(|item| item.id).collect()
+    }
+
+    #[test]
+    fn inline_vec_spills_to_the_heap_when_full() {
+        let mut vec: InlineVec<i32, 3> = InlineVec::new();
+        vec.extend([1, 2, 3]);
+        assert!(!vec.spilled());
+        assert_eq!(vec.capacity(), 3);
+
+        vec.push(4);
+        assert!(vec.spilled());
+        assert_eq!(vec.as_slice(), [1, 2, 3, 4]);
+
+        vec.insert(0, 0);
+        assert_eq!(vec.remove(2), 2);
+        assert_eq!(vec.swap_remove(0), 0);
+        assert_eq!(vec.as_slice(), [4, 1, 3]);
+        assert_eq!(vec.into_vec(), [4, 1, 3]);
+    }
+
+    #[test]
+    fn inline_vec_with_capacity_above_n_starts_on_the_heap() {
+        let small: InlineVec<u8, 4> = InlineVec::with_capacity(4);
+        let large: InlineVec<u8, 4> = InlineVec::with_capacity(5);
+        assert!(!small.spilled());
+        assert!(large.spilled());
+        assert!(large.capacity() >= 5);
+    }
+
+    #[test]
+    fn inline_vec_zero_capacity_spills_on_first_push() {
+        let mut vec: InlineVec<String, 0> = InlineVec::new();
+        vec.push("a".to_string());
+        assert!(vec.spilled());
+        assert_eq!(vec.as_slice(), ["a"]);
+    }
+
+    #[test]
+    fn inline_vec_drops_every_element_once() {
+        let drops = Cell::new(0);
+        let clones_left = Cell::new(usize::MAX);
+        for len in [2, 4, 7] {
+            drops.set(0);
+            let vec = tracked::<4>(len, &drops, &clones_left);
+            let copy = vec.clone();
+            assert_eq!(ids(&copy), ids(&vec));
+            drop(vec);
+            assert_eq!(drops.get(), len);
+            drop(copy);
+            assert_eq!(drops.get(), 2 * len);
+        }
+    }
+
+    #[test]
+    fn inline_vec_truncate_drops_only_the_tail() {
+        let drops = Cell::new(0);
+        let clones_left = Cell::new(0);
+        for len in [4, 6] {
+            drops.set(0);
+            let mut vec = tracked::<4>(len, &drops, &clones_left);
+            vec.truncate(1);
+            assert_eq!(drops.get(), len - 1);
+            assert_eq!(ids(&vec), [0]);
+            vec.truncate(3);
+            assert_eq!(drops.get(), len - 1);
+            vec.clear();
+            assert_eq!(drops.get(), len);
+        }
+    }
+
+    #[test]
+    fn inline_vec_retain_and_pop_keep_drop_counts() {
+        let drops = Cell::new(0);
+        let clones_left = Cell::new(0);
+        let mut vec = tracked::<8>(5, &drops, &clones_left);
+        vec.retain(|item| item.id % 2 == 0);
+        assert_eq!(ids(&vec), [0, 2, 4]);
+        assert_eq!(drops.get(), 2);
+        let last = vec.pop().expect("vector is not empty");
+        assert_eq!(last.id, 4);
+        assert_eq!(drops.get(), 2);
+        drop(last);
+        drop(vec);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn inline_vec_into_iter_drops_the_unyielded_elements() {
+        let drops = Cell::new(0);
+        let clones_left = Cell::new(0);
+        for len in [4, 6] {
+            drops.set(0);
+            let mut iter = tracked::<4>(len, &drops, &clones_left).into_iter();
+            assert_eq!(iter.next().map(|item| item.id), Some(0));
+            assert_eq!(iter.next_back().map(|item| item.id), Some(len - 1));
+            assert_eq!(iter.len(), len - 2);
+            assert_eq!(drops.get(), 2);
+            drop(iter);
+            assert_eq!(drops.get(), len);
+        }
+    }
+
+    #[test]
+    fn inline_vec_clone_panic_drops_the_partial_copy() {
+        let drops = Cell::new(0);
+        let clones_left = Cell::new(usize::MAX);
+        for len in [4, 6] {
+            drops.set(0);
+            let vec = tracked::<4>(len, &drops, &clones_left);
+            clones_left.set(len - 1);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| vec.clone()));
+            assert!(result.is_err());
+            // Only the clones made before the panic have been dropped
+            assert_eq!(drops.get(), len - 1);
+            assert_eq!(ids(&vec), (0..len).collect::<Vec<_>>());
+            drop(vec);
+            assert_eq!(drops.get(), 2 * len - 1);
+        }
+    }
 }
@@ -0,0 +1,311 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+/// Error returned when a graph contains a cycle where none is allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError<N> {
+    cycle: Vec<N>,
+}
+
+impl<N> CycleError<N> {
+    /// Create an error from the nodes of a cycle, in edge order
+    pub fn new(cycle: Vec<N>) -> Self {
+        Self { cycle }
+    }
+
+    /// The nodes forming the cycle, in edge order
+    /// The edge from the last node leads back to the first
+    pub fn cycle(&self) -> &[N] {
+        &self.cycle
+    }
+
+    /// Consume the error and return the cycle
+    pub fn into_cycle(self) -> Vec<N> {
+        self.cycle
+    }
+}
+
+impl<N: fmt::Debug> fmt::Display for CycleError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle detected: ")?;
+        for node in &self.cycle {
+            write!(f, "{:?} -> ", node)?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{:?}", first),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<N: fmt::Debug> Error for CycleError<N> {}
+
+/// A directed graph stored as adjacency lists
+///
+/// Nodes are kept in insertion order, which makes every traversal
+/// and sort deterministic
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::collections::graph::DiGraph;
+///
+/// let mut outfit = DiGraph::new();
+/// outfit.add_edge("shirt", "tie");
+/// outfit.add_edge("tie", "jacket");
+/// outfit.add_edge("shirt", "belt");
+/// outfit.add_edge("belt", "jacket");
+///
+/// assert_eq!(outfit.node_count(), 4);
+/// assert!(outfit.has_edge(&"shirt", &"belt"));
+/// assert_eq!(outfit.topo_sort(), Ok(vec!["shirt", "tie", "belt", "jacket"]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiGraph<N> {
+    nodes: Vec<N>,
+    indices: HashMap<N, usize>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl<N: Eq + Hash + Clone> DiGraph<N> {
+    /// Create an empty graph
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            indices: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Add a node if it is not already present and return its index
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.indices.insert(node.clone(), index);
+        self.nodes.push(node);
+        self.edges.push(Vec::new());
+        index
+    }
+
+    /// Add an edge from `from` to `to`, adding either node if missing
+    /// Duplicate edges are ignored
+    pub fn add_edge(&mut self, from: N, to: N) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        if !self.edges[from].contains(&to) {
+            self.edges[from].push(to);
+        }
+    }
+
+    /// Returns true if the node is present
+    pub fn contains_node(&self, node: &N) -> bool {
+        self.indices.contains_key(node)
+    }
+
+    /// Returns true if there is an edge from `from` to `to`
+    pub fn has_edge(&self, from: &N, to: &N) -> bool {
+        match (self.indices.get(from), self.indices.get(to)) {
+            (Some(&from), Some(to)) => self.edges[from].contains(to),
+            _ => false,
+        }
+    }
+
+    /// Number of nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of edges
+    pub fn edge_count(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    /// Iterate over the nodes in insertion order
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        self.nodes.iter()
+    }
+
+    /// Iterate over the direct successors of a node
+    pub fn successors(&self, node: &N) -> impl Iterator<Item = &N> {
+        self.indices
+            .get(node)
+            .map(|&index| self.edges[index].as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|&index| &self.nodes[index])
+    }
+
+    /// Order the nodes so every edge points from an earlier node to a later one
+    ///
+    /// Uses Kahn's algorithm; whenever several nodes are ready, the one
+    /// inserted first goes next. Fails with one of the offending cycles if
+    /// the graph is not acyclic
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rs_mytools::collections::graph::DiGraph;
+    ///
+    /// let graph: DiGraph<_> = [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")]
+    ///     .into_iter()
+    ///     .collect();
+    /// let error = graph.topo_sort().unwrap_err();
+    /// assert_eq!(error.cycle(), ["b", "c", "a"]);
+    /// assert_eq!(error.to_string(), r#"cycle detected: "b" -> "c" -> "a" -> "b""#);
+    /// ```
+    pub fn topo_sort(&self) -> Result<Vec<N>, CycleError<N>> {
+        let mut in_degree = vec![0usize; self.nodes.len()];
+        for targets in &self.edges {
+            for &to in targets {
+                in_degree[to] += 1;
+            }
+        }
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.nodes.len())
+            .filter(|&index| in_degree[index] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(self.nodes[index].clone());
+            for &to in &self.edges[index] {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    ready.push(Reverse(to));
+                }
+            }
+        }
+        if order.len() == self.nodes.len() {
+            Ok(order)
+        } else {
+            Err(CycleError::new(self.find_cycle(&in_degree)))
+        }
+    }
+
+    /// Group the nodes into strongly connected components
+    ///
+    /// Uses Tarjan's algorithm; components are returned in reverse
+    /// topological order, so each one only has edges into earlier ones
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rs_mytools::collections::graph::DiGraph;
+    ///
+    /// let graph: DiGraph<_> = [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")]
+    ///     .into_iter()
+    ///     .collect();
+    /// assert_eq!(graph.strongly_connected_components(), [vec!["d"], vec!["c", "b", "a"]]);
+    /// ```
+    pub fn strongly_connected_components(&self) -> Vec<Vec<N>> {
+        const UNVISITED: usize = usize::MAX;
+        let count = self.nodes.len();
+        let mut index = vec![UNVISITED; count];
+        let mut low = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut components = Vec::new();
+
+        for start in 0..count {
+            if index[start] != UNVISITED {
+                continue;
+            }
+            index[start] = next_index;
+            low[start] = next_index;
+            next_index += 1;
+            stack.push(start);
+            on_stack[start] = true;
+            let mut calls = vec![(start, 0)];
+
+            while let Some(&(node, edge)) = calls.last() {
+                if let Some(&next) = self.edges[node].get(edge) {
+                    if let Some(call) = calls.last_mut() {
+                        call.1 += 1;
+                    }
+                    if index[next] == UNVISITED {
+                        index[next] = next_index;
+                        low[next] = next_index;
+                        next_index += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        calls.push((next, 0));
+                    } else if on_stack[next] {
+                        low[node] = low[node].min(index[next]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low[parent] = low[parent].min(low[node]);
+                }
+                if low[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(self.nodes[member].clone());
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    // Every node left with a positive in-degree after Kahn's algorithm has a
+    // predecessor that is also left over, so walking predecessors must loop
+    fn find_cycle(&self, in_degree: &[usize]) -> Vec<N> {
+        let remaining = |index: usize| in_degree[index] > 0;
+        let mut predecessor = vec![None; self.nodes.len()];
+        for (from, targets) in self.edges.iter().enumerate() {
+            if remaining(from) {
+                for &to in targets {
+                    predecessor[to].get_or_insert(from);
+                }
+            }
+        }
+
+        let start = (0..self.nodes.len()).find(|&index| remaining(index));
+        let mut seen = vec![false; self.nodes.len()];
+        let mut path: Vec<usize> = Vec::new();
+        let mut current = start;
+        while let Some(index) = current {
+            if seen[index] {
+                let begin = path.iter().position(|&node| node == index).unwrap_or(0);
+                return path[begin..]
+                    .iter()
+                    .rev()
+                    .map(|&node| self.nodes[node].clone())
+                    .collect();
+            }
+            seen[index] = true;
+            path.push(index);
+            current = predecessor[index];
+        }
+        Vec::new()
+    }
+}
+
+impl<N: Eq + Hash + Clone> Default for DiGraph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Eq + Hash + Clone> FromIterator<(N, N)> for DiGraph<N> {
+    fn from_iter<I: IntoIterator<Item = (N, N)>>(iter: I) -> Self {
+        let mut graph = Self::new();
+        for (from, to) in iter {
+            graph.add_edge(from, to);
+        }
+        graph
+    }
+}
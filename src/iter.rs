@@ -15,6 +15,38 @@ pub trait IterExt: Iterator {
         assert!(n > 0, "n must be greater than 0");
         EveryNth { iter: self, n, index: 0 }
     }
+
+    /// Split an iterator of 3-tuples into three Vecs
+    fn unzip3<A, B, C>(self) -> (Vec<A>, Vec<B>, Vec<C>)
+    where
+        Self: Sized + Iterator<Item = (A, B, C)>,
+    {
+        Unzip::unzip_all(self)
+    }
+
+    /// Split an iterator of 4-tuples into four Vecs
+    fn unzip4<A, B, C, D>(self) -> (Vec<A>, Vec<B>, Vec<C>, Vec<D>)
+    where
+        Self: Sized + Iterator<Item = (A, B, C, D)>,
+    {
+        Unzip::unzip_all(self)
+    }
+
+    /// Split an iterator of Results into the Ok values and the errors, keeping every error
+    fn partition_results<T, E>(self) -> (Vec<T>, Vec<E>)
+    where
+        Self: Sized + Iterator<Item = Result<T, E>>,
+    {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for item in self {
+            match item {
+                Ok(value) => values.push(value),
+                Err(e) => errors.push(e),
+            }
+        }
+        (values, errors)
+    }
 }
 
 impl<T: Iterator> IterExt for T {}
@@ -33,11 +65,42 @@ impl<I: Iterator> Iterator for EveryNth<I> {
         loop {
             let item = self.iter.next()?;
             let index = self.index;
-            self.index = self.index + 1;
+            // Wrapping at `n` keeps the position in the current stride
+            self.index = (index + 1) % self.n;
             
-            if index % self.n == 0 {
+            if index == 0 {
                 return Some(item);
             }
         }
     }
 }
+
+/// Tuples whose elements can be collected into one Vec per position
+pub trait Unzip: Sized {
+    /// A tuple with one Vec per element of `Self`
+    type Output;
+
+    /// Split every tuple in the iterator into the per-position Vecs
+    fn unzip_all<I: Iterator<Item = Self>>(iter: I) -> Self::Output;
+}
+
+macro_rules! impl_unzip {
+    ($($name:ident $vec:ident $item:ident),+) => {
+        impl<$($name),+> Unzip for ($($name,)+) {
+            type Output = ($(Vec<$name>,)+);
+
+            fn unzip_all<I: Iterator<Item = Self>>(iter: I) -> Self::Output {
+                let capacity = iter.size_hint().0;
+                $(let mut $vec = Vec::with_capacity(capacity);)+
+                for ($($item,)+) in iter {
+                    $($vec.push($item);)+
+                }
+                ($($vec,)+)
+            }
+        }
+    };
+}
+
+impl_unzip!(A vec_a a, B vec_b b);
+impl_unzip!(A vec_a a, B vec_b b, C vec_c c);
+impl_unzip!(A vec_a a, B vec_b b, C vec_c c, D vec_d d);
@@ -4,12 +4,16 @@ pub mod io;
 pub mod iter;
 pub mod macros;
 pub mod option;
+pub mod random;
 pub mod result;
 pub mod string;
+pub mod sync;
 pub use borrow::*;
 pub use collections::*;
 pub use io::*;
 pub use iter::*;
 pub use option::*;
+pub use random::*;
 pub use result::*;
-pub use string::*;
\ No newline at end of file
+pub use string::*;
+pub use sync::*;
\ No newline at end of file
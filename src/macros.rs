@@ -22,7 +22,7 @@ macro_rules! map {
     // Map with entries
     ($($key:expr => $value:expr),+ $(,)?) => {
         {
-            let mut map = std::collections::HashMap::with_capacity(count!($($key),+));
+            let mut map = std::collections::HashMap::with_capacity($crate::count!($($key),+));
             $(
                 map.insert($key, $value);
             )+
@@ -51,7 +51,7 @@ macro_rules! set {
     // Set with entries
     ($($value:expr),+ $(,)?) => {
         {
-            let mut set = std::collections::HashSet::with_capacity(count!($($value),+));
+            let mut set = std::collections::HashSet::with_capacity($crate::count!($($value),+));
             $(
                 set.insert($value);
             )+
@@ -60,12 +60,133 @@ macro_rules! set {
     };
 }
 
+/// Create a BTreeMap from key-value pairs
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::btree_map;
+///
+/// let versions = btree_map! {
+///     "serde" => "1.0",
+///     "anyhow" => "1.0",
+/// };
+/// assert_eq!(versions.keys().next(), Some(&"anyhow"));
+/// ```
+#[macro_export]
+macro_rules! btree_map {
+    // Empty map
+    () => {
+        std::collections::BTreeMap::new()
+    };
+
+    // Map with entries
+    ($($key:expr => $value:expr),+ $(,)?) => {
+        {
+            let mut map = std::collections::BTreeMap::new();
+            $(
+                map.insert($key, $value);
+            )+
+            map
+        }
+    };
+}
+
+/// Create a BTreeSet from values
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::btree_set;
+///
+/// let levels = btree_set![3, 1, 2];
+/// assert_eq!(levels.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+/// ```
+#[macro_export]
+macro_rules! btree_set {
+    // Empty set
+    () => {
+        std::collections::BTreeSet::new()
+    };
+
+    // Set with entries
+    ($($value:expr),+ $(,)?) => {
+        {
+            let mut set = std::collections::BTreeSet::new();
+            $(
+                set.insert($value);
+            )+
+            set
+        }
+    };
+}
+
+/// Create a VecDeque from a sequence of values
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::deque;
+///
+/// let mut window = deque![1, 2, 3];
+/// window.pop_front();
+/// window.push_back(4);
+/// assert_eq!(window, [2, 3, 4]);
+/// ```
+#[macro_export]
+macro_rules! deque {
+    // Empty deque
+    () => {
+        std::collections::VecDeque::new()
+    };
+
+    // Deque with entries
+    ($($x:expr),+ $(,)?) => {
+        {
+            let mut deque = std::collections::VecDeque::with_capacity($crate::count!($($x),+));
+            $(
+                deque.push_back($x);
+            )+
+            deque
+        }
+    };
+}
+
+/// Create a BinaryHeap (max-heap) from a sequence of values
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::heap;
+///
+/// let mut tasks = heap![2, 7, 4];
+/// assert_eq!(tasks.pop(), Some(7));
+/// ```
+#[macro_export]
+macro_rules! heap {
+    // Empty heap
+    () => {
+        std::collections::BinaryHeap::new()
+    };
+
+    // Heap with entries
+    ($($x:expr),+ $(,)?) => {
+        {
+            let mut heap = std::collections::BinaryHeap::with_capacity($crate::count!($($x),+));
+            $(
+                heap.push($x);
+            )+
+            heap
+        }
+    };
+}
+
 /// Count the number of arguments (used internally)
 #[macro_export]
 #[doc(hidden)]
 macro_rules! count {
     () => (0);
-    ($head:expr $(, $tail:expr)*) => (1 + count!($($tail),*));
+    ($head:expr $(, $tail:expr)*) => (1 + $crate::count!($($tail),*));
 }
 
 /// Try to execute an expression and return early with the error if it fails
@@ -94,8 +215,227 @@ macro_rules! try_or_return {
     };
 }
 
+/// Unwrap an `Option` or `Result` or run a failure action (used internally)
+#[doc(hidden)]
+pub trait TryOrValue {
+    type Value;
+
+    fn into_value(self) -> Option<Self::Value>;
+}
+
+impl<T> TryOrValue for Option<T> {
+    type Value = T;
+
+    fn into_value(self) -> Option<T> {
+        self
+    }
+}
+
+impl<T, E> TryOrValue for Result<T, E> {
+    type Value = T;
+
+    fn into_value(self) -> Option<T> {
+        self.ok()
+    }
+}
+
+/// Unwrap an `Option` or `Result`, or leave the current function or loop
+///
+/// With no action the enclosing function returns `None`. The action can be
+/// `continue` or `break` (optionally labelled), `return`, or a value for the
+/// function to return
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::try_or;
+///
+/// fn first_char_upper(text: &str) -> Option<char> {
+///     let c = try_or!(text.chars().next());
+///     Some(c.to_ascii_uppercase())
+/// }
+/// assert_eq!(first_char_upper(""), None);
+///
+/// fn is_valid_port(text: &str) -> bool {
+///     let port: u16 = try_or!(text.parse(), false);
+///     port != 0
+/// }
+/// assert!(!is_valid_port("http"));
+///
+/// let mut total = 0;
+/// for text in ["1", "x", "3"] {
+///     total += try_or!(text.parse::<i32>(), continue);
+/// }
+/// assert_eq!(total, 4);
+/// ```
+#[macro_export]
+macro_rules! try_or {
+    // Run the failure action when there is no value
+    (@unwrap $expr:expr, $($action:tt)+) => {
+        match $crate::macros::TryOrValue::into_value($expr) {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => $($action)+,
+        }
+    };
+    ($expr:expr $(,)?) => {
+        $crate::try_or!(@unwrap $expr, return ::core::option::Option::None)
+    };
+    ($expr:expr, continue $($label:lifetime)? $(,)?) => {
+        $crate::try_or!(@unwrap $expr, continue $($label)?)
+    };
+    ($expr:expr, break $($label:lifetime)? $(,)?) => {
+        $crate::try_or!(@unwrap $expr, break $($label)?)
+    };
+    ($expr:expr, return $($value:expr)? $(,)?) => {
+        $crate::try_or!(@unwrap $expr, return $($value)?)
+    };
+    ($expr:expr, $value:expr $(,)?) => {
+        $crate::try_or!(@unwrap $expr, return $value)
+    };
+}
+
+/// Return early with an error if a condition does not hold
+///
+/// The error is converted with `From`, just like the `?` operator
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::ensure;
+///
+/// fn withdraw(balance: u32, amount: u32) -> Result<u32, String> {
+///     ensure!(amount <= balance, format!("insufficient funds: {} < {}", balance, amount));
+///     Ok(balance - amount)
+/// }
+/// assert_eq!(withdraw(10, 3), Ok(7));
+/// assert!(withdraw(1, 3).is_err());
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $err:expr $(,)?) => {
+        if !$cond {
+            return Err(::core::convert::From::from($err));
+        }
+    };
+}
+
+/// Destructure a value or run a diverging `else` branch
+///
+/// Binds the pattern's variables in the enclosing scope, like `let ... else`
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::guard;
+///
+/// fn first_word_len(text: &str) -> usize {
+///     guard!(let Some(word) = text.split_whitespace().next(), else return 0);
+///     word.len()
+/// }
+/// assert_eq!(first_word_len("hello world"), 5);
+/// assert_eq!(first_word_len("   "), 0);
+/// ```
+#[macro_export]
+macro_rules! guard {
+    (let $pat:pat = $expr:expr, else $($else:tt)+) => {
+        let $pat = $expr else { $($else)+ };
+    };
+}
+
+/// Evaluate a Result-producing expression up to `attempts` times, sleeping between tries
+///
+/// Returns the first `Ok`, or the last `Err` once every attempt has failed.
+/// Add `backoff = factor` to multiply the delay after each failure; the
+/// factor must be finite and non-negative, and the delay saturates at
+/// `Duration::MAX`
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::retry;
+/// use std::time::Duration;
+///
+/// let mut calls = 0;
+/// let result: Result<u32, &str> = retry!(3, Duration::from_millis(1), {
+///     calls += 1;
+///     if calls < 3 { Err("flaky") } else { Ok(calls) }
+/// });
+/// assert_eq!(result, Ok(3));
+///
+/// let result: Result<(), &str> = retry!(2, Duration::from_millis(1), backoff = 2.0, Err("down"));
+/// assert_eq!(result, Err("down"));
+/// ```
+#[macro_export]
+macro_rules! retry {
+    // Exponential backoff between attempts
+    ($attempts:expr, $delay:expr, backoff = $factor:expr, $body:expr) => {
+        {
+            let attempts: usize = $attempts;
+            let factor: f64 = $factor;
+            assert!(factor.is_finite() && factor >= 0.0, "backoff factor must be finite and non-negative");
+            let mut delay: std::time::Duration = $delay;
+            let mut attempt = 1;
+            loop {
+                match $body {
+                    Ok(value) => break Ok(value),
+                    Err(err) if attempt >= attempts => break Err(err),
+                    Err(_) => {
+                        attempt += 1;
+                        std::thread::sleep(delay);
+                        // Saturate instead of panicking once the delay outgrows `Duration`
+                        delay = std::time::Duration::try_from_secs_f64(delay.as_secs_f64() * factor)
+                            .unwrap_or(std::time::Duration::MAX);
+                    }
+                }
+            }
+        }
+    };
+
+    // Fixed delay between attempts
+    ($attempts:expr, $delay:expr, $body:expr) => {
+        $crate::retry!($attempts, $delay, backoff = 1.0, $body)
+    };
+}
+
+/// Whether `dbg_print!` output is kept in release builds (used internally)
+#[doc(hidden)]
+pub const DEBUG_PRINTS: bool = cfg!(feature = "debug-prints");
+
+/// Check a module path against the `MYTOOLS_DEBUG` filter (used internally)
+///
+/// The variable holds a comma-separated list of module prefixes, with or
+/// without the crate name. When it is unset or empty every module is enabled
+#[doc(hidden)]
+pub fn debug_print_enabled(module_path: &str) -> bool {
+    static FILTER: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+    let filter = FILTER.get_or_init(|| {
+        std::env::var("MYTOOLS_DEBUG")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|prefix| !prefix.is_empty())
+            .map(String::from)
+            .collect()
+    });
+    if filter.is_empty() {
+        return true;
+    }
+    let has_prefix = |path: &str, prefix: &str| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    };
+    let without_crate = module_path.split_once("::").map(|(_, rest)| rest);
+    filter.iter().any(|prefix| {
+        has_prefix(module_path, prefix) || without_crate.is_some_and(|path| has_prefix(path, prefix))
+    })
+}
+
 /// Debug print a value with its name and location
 ///
+/// Printing is compiled out of release builds unless the `debug-prints`
+/// feature is enabled. Set `MYTOOLS_DEBUG=io,iter` to only print from
+/// matching modules. The value is always evaluated once and returned
+///
 /// # Examples
 ///
 /// ```
@@ -107,22 +447,92 @@ macro_rules! try_or_return {
 #[macro_export]
 macro_rules! dbg_print {
     ($val:expr) => {
-        {
-            eprintln!("[{}:{}] {} = {:?}",
-                file!(), line!(), stringify!($val), $val);
-            $val
+        match $val {
+            val => {
+                if (cfg!(debug_assertions) || $crate::macros::DEBUG_PRINTS)
+                    && $crate::macros::debug_print_enabled(module_path!())
+                {
+                    eprintln!("[{}:{}] {} = {:?}",
+                        file!(), line!(), stringify!($val), val);
+                }
+                val
+            }
         }
     };
     
     ($val:expr, $($arg:tt)+) => {
+        match $val {
+            val => {
+                if (cfg!(debug_assertions) || $crate::macros::DEBUG_PRINTS)
+                    && $crate::macros::debug_print_enabled(module_path!())
+                {
+                    eprintln!("[{}:{}] {} = {:?} // {}",
+                        file!(), line!(), stringify!($val), val, format!($($arg)+));
+                }
+                val
+            }
+        }
+    };
+}
+
+/// Time a block, print the elapsed duration to stderr, and return the block's value
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::timeit;
+///
+/// let sum = timeit!("sum", {
+///     (1..=100).sum::<u32>()
+/// }); // prints: [src/main.rs:4] sum took 1.2µs
+/// assert_eq!(sum, 5050);
+/// ```
+#[macro_export]
+macro_rules! timeit {
+    ($label:expr, $body:block) => {
         {
-            eprintln!("[{}:{}] {} = {:?} // {}",
-                file!(), line!(), stringify!($val), $val, format!($($arg)+));
-            $val
+            let start = std::time::Instant::now();
+            let result = $body;
+            eprintln!("[{}:{}] {} took {:?}",
+                file!(), line!(), $label, start.elapsed());
+            result
         }
     };
 }
 
+/// Run cleanup code when the current scope exits, including on early return or panic
+///
+/// Use `defer!(name => ...)` to bind the underlying `ScopeGuard` so it can be
+/// dismissed. Multiple deferred blocks run in reverse order
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::defer;
+/// use std::cell::RefCell;
+///
+/// let log = RefCell::new(Vec::new());
+/// {
+///     defer! { log.borrow_mut().push("cleanup"); }
+///     defer!(commit => log.borrow_mut().push("rollback"));
+///     log.borrow_mut().push("work");
+///     commit.dismiss();
+/// }
+/// assert_eq!(*log.borrow(), ["work", "cleanup"]);
+/// ```
+#[macro_export]
+macro_rules! defer {
+    // Named guard that can be dismissed
+    ($guard:ident => $($body:tt)*) => {
+        let $guard = $crate::borrow::ScopeGuard::new(|| { $($body)*; });
+    };
+
+    // Anonymous guard
+    ($($body:tt)*) => {
+        let _guard = $crate::borrow::ScopeGuard::new(|| { $($body)*; });
+    };
+}
+
 /// Create a vec from a sequence of values
 ///
 /// # Examples
@@ -143,7 +553,7 @@ macro_rules! vec {
     // Vector with entries
     ($($x:expr),+ $(,)?) => {
         {
-            let mut vec = Vec::with_capacity(count!($($x),+));
+            let mut vec = Vec::with_capacity($crate::count!($($x),+));
             $(
                 vec.push($x);
             )+
@@ -161,6 +571,110 @@ macro_rules! vec {
     };
 }
 
+/// Declare statics that are computed on first access
+///
+/// Each static becomes a `Lazy<T>` that dereferences to `T`
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::lazy;
+/// use std::collections::HashMap;
+///
+/// lazy! {
+///     static SQUARES: Vec<u64> = (0..10).map(|n| n * n).collect();
+///     pub static CODES: HashMap<&'static str, u16> = {
+///         let mut codes = HashMap::new();
+///         codes.insert("ok", 200);
+///         codes
+///     };
+/// }
+///
+/// assert_eq!(SQUARES[3], 9);
+/// assert_eq!(CODES["ok"], 200);
+/// ```
+#[macro_export]
+macro_rules! lazy {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::borrow::Lazy<$ty> = $crate::borrow::Lazy::new(|| $init);
+        )*
+    };
+}
+
+/// Compile a wildcard pattern once and return a `&'static Wildcard`
+///
+/// The pattern is compiled on first use and cached, so this is cheap to
+/// call in hot loops. Panics on first use if the pattern is invalid
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::pattern;
+///
+/// let logs = ["app.log", "app.log.1", "error.txt"];
+/// let matched: Vec<_> = logs.iter().filter(|name| pattern!("*.log*").is_match(name)).collect();
+/// assert_eq!(matched, [&"app.log", &"app.log.1"]);
+/// assert!(pattern!("v[0-9].?").is_match("v1.x"));
+/// ```
+#[macro_export]
+macro_rules! pattern {
+    ($pattern:literal) => {
+        {
+            static PATTERN: $crate::borrow::Lazy<$crate::string::Wildcard> = $crate::borrow::Lazy::new(|| {
+                $crate::string::Wildcard::new($pattern).expect("invalid wildcard pattern")
+            });
+            &*PATTERN
+        }
+    };
+}
+
+/// Pick an expression based on `cfg` predicates, like a `match` over the target platform
+///
+/// The first arm whose predicate holds is compiled and the others are
+/// discarded entirely, so each arm may use platform-specific APIs.
+/// An optional `else` arm is used when nothing matches; without one,
+/// a non-matching target fails to compile
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::cfg_select;
+///
+/// let separator = cfg_select! {
+///     windows => '\\',
+///     unix => '/',
+///     else => '/',
+/// };
+/// assert_eq!(separator, std::path::MAIN_SEPARATOR);
+/// ```
+#[macro_export]
+macro_rules! cfg_select {
+    // Fallback arm
+    (@arms else => $value:expr $(,)?) => {
+        { $value }
+    };
+
+    // Predicate arm followed by the remaining arms
+    (@arms $cfg:meta => $value:expr $(, $($rest:tt)*)?) => {
+        {
+            #[cfg($cfg)]
+            { $value }
+            #[cfg(not($cfg))]
+            { $crate::cfg_select!(@arms $($($rest)*)?) }
+        }
+    };
+
+    (@arms) => {
+        compile_error!("none of the cfg_select! predicates matched")
+    };
+
+    ($($arms:tt)+) => {
+        $crate::cfg_select!(@arms $($arms)+)
+    };
+}
+
 /// A more concise version of match for Options
 ///
 /// # Examples
@@ -169,9 +683,9 @@ macro_rules! vec {
 /// use rs_mytools::when;
 ///
 /// let opt = Some(42);
-/// let result = when!(opt {
+/// let result = when!(opt, {
 ///     Some(val) => val * 2,
-///     None => 0,
+///     None => 0
 /// });
 /// assert_eq!(result, 84);
 /// ```
@@ -200,38 +714,779 @@ macro_rules! when {
     };
 }
 
-/// Assert multiple conditions at once
+/// Declare a fieldless enum with string names, generating `FromStr`, `Display`,
+/// an `as_str` method, and an `ALL` constant listing every variant
 ///
 /// # Examples
 ///
 /// ```
-/// use rs_mytools::assert_all;
+/// use rs_mytools::enum_str;
 ///
-/// let x = 5;
-/// let y = 10;
-/// assert_all!(
-///     x < y,
-///     x > 0,
-///     y % x == 0
-/// );
+/// enum_str! {
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     pub enum Color {
+///         Red = "red",
+///         Green = "green",
+///     }
+/// }
+///
+/// assert_eq!("green".parse::<Color>(), Ok(Color::Green));
+/// assert_eq!(Color::Red.to_string(), "red");
+/// assert_eq!(Color::ALL, &[Color::Red, Color::Green]);
+/// assert!("blue".parse::<Color>().is_err());
 /// ```
 #[macro_export]
-macro_rules! assert_all {
-    ($($cond:expr),+ $(,)?) => {
-        {
-            let mut all_passed = true;
-            let mut failures = Vec::new();
-            
-            $(
-                if !$cond {
-                    all_passed = false;
-                    failures.push(stringify!($cond));
-                }
-            )+
-            
-            if !all_passed {
-                panic!("assertion failed: {:?}", failures);
+macro_rules! enum_str {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident = $text:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            $($(#[$variant_attr])* $variant,)+
+        }
+
+        impl $name {
+            /// Every variant, in declaration order
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// The string name of this variant
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $text,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($text => Ok($name::$variant),)+
+                    _ => Err(format!("unknown {} {:?}, expected one of: {}",
+                        stringify!($name), s, [$($text),+].join(", "))),
+                }
+            }
+        }
+    };
+}
+
+/// Declare a struct together with a builder for it
+///
+/// Fields with `= default` are optional; all others must be set before
+/// `build()` succeeds. Setters take the field's own type, so literals infer
+/// as usual. An optional `validate` closure can reject the finished value
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::builder;
+///
+/// builder! {
+///     #[derive(Debug)]
+///     pub struct Server => ServerBuilder {
+///         pub host: String,
+///         pub port: u16 = 8080,
+///         pub workers: usize = 4,
+///     }
+///     validate |server: &Server| {
+///         if server.workers == 0 { Err("workers must be positive") } else { Ok(()) }
+///     }
+/// }
+///
+/// let server = Server::builder().host("localhost".into()).port(3000).build().unwrap();
+/// assert_eq!((server.host.as_str(), server.port, server.workers), ("localhost", 3000, 4));
+///
+/// assert_eq!(ServerBuilder::default().build().unwrap_err(), "missing required field `host`");
+/// assert!(Server::builder().host("x".into()).workers(0).build().is_err());
+/// ```
+#[macro_export]
+macro_rules! builder {
+    // Field with a default value
+    (@field $builder:ident . $field:ident = $default:expr) => {
+        $builder.$field.unwrap_or_else(|| $default)
+    };
+
+    // Required field
+    (@field $builder:ident . $field:ident =) => {
+        $builder.$field.ok_or_else(|| format!("missing required field `{}`", stringify!($field)))?
+    };
+
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident => $builder:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $ty:ty $(= $default:expr)?),+ $(,)?
+        }
+        $(validate $validator:expr)?
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: $ty,)+
+        }
+
+        impl $name {
+            /// Start building a new value
+            pub fn builder() -> $builder {
+                $builder::default()
+            }
+        }
+
+        /// Builder for
+        #[doc = stringify!($name)]
+        #[derive(Default)]
+        $vis struct $builder {
+            $($field: Option<$ty>,)+
+        }
+
+        impl $builder {
+            $(
+                /// Set the
+                #[doc = stringify!($field)]
+                /// field
+                pub fn $field(mut self, value: $ty) -> Self {
+                    self.$field = Some(value);
+                    self
+                }
+            )+
+
+            /// Build the value, failing if a required field is missing or validation fails
+            pub fn build(self) -> Result<$name, String> {
+                let built = $name {
+                    $($field: $crate::builder!(@field self.$field = $($default)?),)+
+                };
+                $(($validator)(&built)?;)?
+                Ok(built)
+            }
+        }
+    };
+}
+
+/// Assert multiple conditions at once
+///
+/// Every condition is evaluated, and the panic message lists each one that
+/// failed. For a top-level comparison the evaluated operands are included,
+/// which requires them to implement `Debug`. Conditions containing a
+/// turbofish or a qualified path such as `<T as Trait>::X` are checked
+/// as a whole, without the operands
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::assert_all;
+///
+/// trait Limit {
+///     const MAX: i32;
+/// }
+///
+/// impl Limit for u8 {
+///     const MAX: i32 = 255;
+/// }
+///
+/// let x = 5;
+/// let y = 10;
+/// assert_all!(
+///     x < y,
+///     x > 0,
+///     y % x == 0,
+///     Vec::<(i32, i32)>::new().is_empty(),
+///     <u8 as Limit>::MAX > y,
+///     y < <u8 as Limit>::MAX
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_all {
+    // Let the expression parser find where the first condition ends,
+    // which handles commas inside turbofish generics
+    (@split $failures:ident) => {};
+    (@split $failures:ident $($rest:tt)+) => {
+        $crate::assert_all!(@first $failures [$($rest)+] $($rest)+);
+    };
+    (@first $failures:ident [$($all:tt)+] $cond:expr , $($rest:tt)*) => {
+        $crate::assert_all!(@drop $failures [$($all)+] [$($all)+] [, $($rest)*]);
+        $crate::assert_all!(@split $failures $($rest)*);
+    };
+    (@first $failures:ident [$($all:tt)+] $cond:expr) => {
+        $crate::assert_all!(@lead $failures [$($all)+]);
+    };
+    // The condition's tokens are all tokens minus the rest: drop as many
+    // tokens as the rest has, then take that many fewer from the front.
+    // Both steps go eight tokens at a time to stay under the recursion limit
+    (@drop $failures:ident [$($all:tt)+]
+        [$h1:tt $h2:tt $h3:tt $h4:tt $h5:tt $h6:tt $h7:tt $h8:tt $($tail:tt)*]
+        [$s1:tt $s2:tt $s3:tt $s4:tt $s5:tt $s6:tt $s7:tt $s8:tt $($skips:tt)*]) => {
+        $crate::assert_all!(@drop $failures [$($all)+] [$($tail)*] [$($skips)*]);
+    };
+    (@drop $failures:ident [$($all:tt)+] [$head:tt $($tail:tt)*] [$skip:tt $($skips:tt)*]) => {
+        $crate::assert_all!(@drop $failures [$($all)+] [$($tail)*] [$($skips)*]);
+    };
+    (@drop $failures:ident [$($all:tt)+] [$($left:tt)+] []) => {
+        $crate::assert_all!(@take $failures [] [$($all)+] [$($left)+]);
+    };
+    (@take $failures:ident [$($cond:tt)*]
+        [$n1:tt $n2:tt $n3:tt $n4:tt $n5:tt $n6:tt $n7:tt $n8:tt $($all:tt)*]
+        [$c1:tt $c2:tt $c3:tt $c4:tt $c5:tt $c6:tt $c7:tt $c8:tt $($counts:tt)*]) => {
+        $crate::assert_all!(@take $failures [$($cond)* $n1 $n2 $n3 $n4 $n5 $n6 $n7 $n8] [$($all)*] [$($counts)*]);
+    };
+    (@take $failures:ident [$($cond:tt)*] [$next:tt $($all:tt)*] [$count:tt $($counts:tt)*]) => {
+        $crate::assert_all!(@take $failures [$($cond)* $next] [$($all)*] [$($counts)*]);
+    };
+    (@take $failures:ident [$($cond:tt)+] [$($all:tt)*] []) => {
+        $crate::assert_all!(@lead $failures [$($cond)+]);
+    };
+    // A leading `<` opens a qualified path, not a comparison
+    (@lead $failures:ident [< $($cond:tt)+]) => {
+        $crate::assert_all!(@plain $failures < $($cond)+);
+    };
+    (@lead $failures:ident [$($cond:tt)+]) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($cond)+);
+    };
+    // Boolean operators bind looser than comparisons, so check the whole condition
+    (@scan $failures:ident [$($cond:tt)+] && $($rest:tt)*) => {
+        $crate::assert_all!(@plain $failures $($cond)+);
+    };
+    (@scan $failures:ident [$($cond:tt)+] || $($rest:tt)*) => {
+        $crate::assert_all!(@plain $failures $($cond)+);
+    };
+    // A turbofish would be mistaken for a comparison
+    (@scan $failures:ident [$($cond:tt)+] :: < $($rest:tt)*) => {
+        $crate::assert_all!(@plain $failures $($cond)+);
+    };
+    // After an operand `<` is a comparison, after an operator it opens a qualified path
+    (@scan $failures:ident [$($cond:tt)+] $prev:ident < $($rest:tt)*) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($rest)*);
+    };
+    (@scan $failures:ident [$($cond:tt)+] $prev:literal < $($rest:tt)*) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($rest)*);
+    };
+    (@scan $failures:ident [$($cond:tt)+] ($($group:tt)*) < $($rest:tt)*) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($rest)*);
+    };
+    (@scan $failures:ident [$($cond:tt)+] [$($group:tt)*] < $($rest:tt)*) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($rest)*);
+    };
+    (@scan $failures:ident [$($cond:tt)+] $prev:tt < $($rest:tt)*) => {
+        $crate::assert_all!(@plain $failures $($cond)+);
+    };
+    (@scan $failures:ident [$($cond:tt)+] $next:tt $($rest:tt)*) => {
+        $crate::assert_all!(@scan $failures [$($cond)+] $($rest)*);
+    };
+    (@scan $failures:ident [$($cond:tt)+]) => {
+        $crate::assert_all!(@check $failures [$($cond)+] [] $($cond)+);
+    };
+    // Split a comparison into its operands
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] == $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] == [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] != $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] != [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] <= $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] <= [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] >= $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] >= [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] < $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] < [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)+] > $($rhs:tt)+) => {
+        $crate::assert_all!(@compare $failures [$($cond)+] [$($lhs)+] > [$($rhs)+]);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::assert_all!(@check $failures [$($cond)+] [$($lhs)* $next] $($rest)*);
+    };
+    (@check $failures:ident [$($cond:tt)+] [$($lhs:tt)*]) => {
+        $crate::assert_all!(@plain $failures $($cond)+);
+    };
+    // Evaluate both operands once and record their values on failure
+    (@compare $failures:ident [$($cond:tt)+] [$($lhs:tt)+] $op:tt [$($rhs:tt)+]) => {
+        match (&($($lhs)+), &($($rhs)+)) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    $failures.push(format!(
+                        "`{}` (left: {:?}, right: {:?})",
+                        stringify!($($cond)+),
+                        left,
+                        right
+                    ));
+                }
+            }
+        }
+    };
+    (@plain $failures:ident $($cond:tt)+) => {
+        if !($($cond)+) {
+            $failures.push(format!("`{}`", stringify!($($cond)+)));
+        }
+    };
+    ($($conds:tt)+) => {
+        {
+            let mut failures: Vec<String> = Vec::new();
+            $crate::assert_all!(@split failures $($conds)+);
+            if !failures.is_empty() {
+                panic!("assertion failed: {}", failures.join(", "));
             }
         }
     };
 }
+
+/// Assert that a value matches a pattern, printing the value on failure
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::assert_matches;
+///
+/// let reply: Result<u16, String> = Ok(204);
+/// assert_matches!(reply, Ok(200..=299));
+/// assert_matches!(Some(5), Some(n) if n > 3);
+/// ```
+#[macro_export]
+macro_rules! assert_matches {
+    ($value:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pattern $(if $guard)? => {}
+            ref value => panic!(
+                "assertion failed: value does not match `{}`\n  value: {:?}",
+                stringify!($pattern $(if $guard)?), value
+            ),
+        }
+    };
+}
+
+/// Assert that a Result is `Ok` and return the contained value
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::assert_ok;
+///
+/// let port = assert_ok!("8080".parse::<u16>());
+/// assert_eq!(port, 8080);
+/// ```
+#[macro_export]
+macro_rules! assert_ok {
+    ($result:expr $(,)?) => {
+        match $result {
+            Ok(value) => value,
+            Err(err) => panic!("assertion failed: expected Ok, got Err({:?})", err),
+        }
+    };
+}
+
+/// Assert that a Result is `Err` and return the contained error
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::assert_err;
+///
+/// let err = assert_err!("eighty".parse::<u16>());
+/// assert_eq!(err.to_string(), "invalid digit found in string");
+/// ```
+#[macro_export]
+macro_rules! assert_err {
+    ($result:expr $(,)?) => {
+        match $result {
+            Ok(value) => panic!("assertion failed: expected Err, got Ok({:?})", value),
+            Err(err) => err,
+        }
+    };
+}
+
+/// Check whether a value matches any of several patterns
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::matches_any;
+///
+/// enum Token { Plus, Minus, Star, Number(i64) }
+///
+/// let token = Token::Minus;
+/// assert!(matches_any!(token, Token::Plus, Token::Minus));
+/// assert!(!matches_any!(Token::Star, Token::Plus, Token::Minus, Token::Number(_)));
+/// assert!(matches_any!(304, 200..=299, 304));
+/// ```
+#[macro_export]
+macro_rules! matches_any {
+    ($value:expr, $($pattern:pat),+ $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $($pattern)|+ $(if $guard)? => true,
+            _ => false,
+        }
+    };
+}
+
+/// Thread a value through a chain of calls, left to right
+///
+/// Each step is a function or closure applied to the previous result. A step
+/// written as a call, like `f(a, b)`, receives the value as its first argument
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::pipe;
+///
+/// fn double(x: i32) -> i32 { x * 2 }
+/// fn add(x: i32, y: i32) -> i32 { x + y }
+///
+/// let result = pipe!(3 => double => add(4) => |x| x * 10 => i32::wrapping_neg);
+/// assert_eq!(result, -100);
+/// ```
+#[macro_export]
+macro_rules! pipe {
+    // A call with extra arguments receives the value first
+    (@step $value:expr; $($f:ident)::+ ($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::pipe!(@next $($f)::+($value, $($args)*) $(=> $($rest)+)?)
+    };
+    // Any other callable expression, including closures
+    (@step $value:expr; $f:expr $(=> $($rest:tt)+)?) => {
+        $crate::pipe!(@next ($f)($value) $(=> $($rest)+)?)
+    };
+    (@next $value:expr) => {
+        $value
+    };
+    (@next $value:expr => $($rest:tt)+) => {
+        $crate::pipe!(@step $value; $($rest)+)
+    };
+    ($input:expr $(=> $($steps:tt)+)?) => {
+        $crate::pipe!(@next $input $(=> $($steps)+)?)
+    };
+}
+
+/// Build any `FromIterator` collection from a list of values or key-value pairs
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::collect;
+/// use std::collections::{BTreeMap, LinkedList};
+///
+/// let ports = collect![BTreeMap<_, _>; "http" => 80, "https" => 443];
+/// assert_eq!(ports["https"], 443);
+///
+/// let queue = collect![LinkedList<_>; 1, 2, 3];
+/// assert_eq!(queue.front(), Some(&1));
+///
+/// let empty = collect![Vec<u8>];
+/// assert!(empty.is_empty());
+/// ```
+#[macro_export]
+macro_rules! collect {
+    // Empty collection
+    ($ty:ty $(;)?) => {
+        ::core::iter::empty().collect::<$ty>()
+    };
+
+    // Key-value pairs
+    ($ty:ty; $($key:expr => $value:expr),+ $(,)?) => {
+        ::core::iter::IntoIterator::into_iter([$(($key, $value)),+]).collect::<$ty>()
+    };
+
+    // Plain values
+    ($ty:ty; $($value:expr),+ $(,)?) => {
+        ::core::iter::IntoIterator::into_iter([$($value),+]).collect::<$ty>()
+    };
+}
+
+/// Declare `StaticMap` lookup tables built at compile time
+///
+/// Each static becomes a `StaticMap<V>` for the declared value type `V`. The
+/// entries are sorted during constant evaluation, so no lazy initialization
+/// is needed. Duplicate keys are a compile error
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::static_map;
+///
+/// static_map! {
+///     pub static MIME_TYPES: &str = {
+///         "html" => "text/html",
+///         "css" => "text/css",
+///         "png" => "image/png",
+///     };
+///     static KEYWORDS: u8 = { "fn" => 1, "let" => 2, "if" => 3 };
+/// }
+///
+/// assert_eq!(MIME_TYPES.get("css"), Some(&"text/css"));
+/// assert_eq!(MIME_TYPES.get("exe"), None);
+/// assert_eq!(MIME_TYPES.keys().collect::<Vec<_>>(), ["css", "html", "png"]);
+/// assert!(KEYWORDS.contains_key("let"));
+/// ```
+#[macro_export]
+macro_rules! static_map {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = { $($key:expr => $value:expr),* $(,)? };)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::collections::StaticMap<$ty> = {
+                const ENTRIES: &[(&str, $ty)] =
+                    &$crate::collections::sort_static_entries([$(($key, $value)),*]);
+                $crate::collections::StaticMap::from_sorted(ENTRIES)
+            };
+        )*
+    };
+}
+
+/// Join literals with a separator at compile time, producing a `&'static str`
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::concat_sep;
+///
+/// const USERS_PATH: &str = concat_sep!("/", "api", "v1", "users");
+/// assert_eq!(USERS_PATH, "api/v1/users");
+/// assert_eq!(concat_sep!(", ", "id", "name", 42), "id, name, 42");
+/// assert_eq!(concat_sep!("/"), "");
+/// ```
+#[macro_export]
+macro_rules! concat_sep {
+    ($sep:literal $(,)?) => {
+        ""
+    };
+    ($sep:literal, $first:literal $(, $rest:literal)* $(,)?) => {
+        ::core::concat!($first $(, $sep, $rest)*)
+    };
+}
+
+/// Timing summary produced by `bench!`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchStats {
+    pub iterations: usize,
+    pub min: std::time::Duration,
+    pub mean: std::time::Duration,
+    pub p95: std::time::Duration,
+    pub max: std::time::Duration,
+}
+
+impl BenchStats {
+    /// Summarize a set of per-iteration timings
+    pub fn from_samples(mut samples: Vec<std::time::Duration>) -> Self {
+        samples.sort_unstable();
+        let iterations = samples.len();
+        if iterations == 0 {
+            let zero = std::time::Duration::ZERO;
+            return Self { iterations, min: zero, mean: zero, p95: zero, max: zero };
+        }
+        let total: std::time::Duration = samples.iter().sum();
+        let p95_index = (iterations * 95).div_ceil(100) - 1;
+        Self {
+            iterations,
+            min: samples[0],
+            mean: std::time::Duration::from_nanos((total.as_nanos() / iterations as u128) as u64),
+            p95: samples[p95_index],
+            max: samples[iterations - 1],
+        }
+    }
+}
+
+impl std::fmt::Display for BenchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} iterations: min {:?}, mean {:?}, p95 {:?}, max {:?}",
+            self.iterations, self.min, self.mean, self.p95, self.max
+        )
+    }
+}
+
+/// Run a block repeatedly, print timing statistics to stderr, and return them
+///
+/// Each result is passed through `std::hint::black_box` so the work is not
+/// optimized away
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::bench;
+///
+/// let stats = bench!(100, {
+///     (1..=1000u64).map(|n| n * n).sum::<u64>()
+/// }); // prints: [src/main.rs:4] 100 iterations: min 1.1µs, mean 1.3µs, p95 1.9µs, max 4µs
+/// assert_eq!(stats.iterations, 100);
+/// assert!(stats.min <= stats.p95 && stats.p95 <= stats.max);
+/// ```
+#[macro_export]
+macro_rules! bench {
+    ($iterations:expr, $body:block) => {
+        {
+            let iterations: usize = $iterations;
+            let mut samples = Vec::with_capacity(iterations);
+            for _ in 0..iterations {
+                let start = std::time::Instant::now();
+                std::hint::black_box($body);
+                samples.push(start.elapsed());
+            }
+            let stats = $crate::macros::BenchStats::from_samples(samples);
+            eprintln!("[{}:{}] {}", file!(), line!(), stats);
+            stats
+        }
+    };
+}
+
+/// Swap two places, such as fields of the same or different structs
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::swap_fields;
+///
+/// struct Point { x: i32, y: i32 }
+///
+/// let mut a = Point { x: 1, y: 2 };
+/// let mut b = Point { x: 3, y: 4 };
+/// swap_fields!(a.x, b.y);
+/// assert_eq!((a.x, b.y), (4, 1));
+/// swap_fields!(a.x, a.y);
+/// assert_eq!((a.x, a.y), (2, 4));
+/// ```
+#[macro_export]
+macro_rules! swap_fields {
+    ($a:expr, $b:expr $(,)?) => {
+        ::core::mem::swap(&mut $a, &mut $b)
+    };
+}
+
+/// Rotate three places to the left, so `a` takes `b`, `b` takes `c` and `c` takes `a`
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::rotate3;
+///
+/// let (mut a, mut b, mut c) = (String::from("a"), String::from("b"), String::from("c"));
+/// rotate3!(a, b, c);
+/// assert_eq!((a.as_str(), b.as_str(), c.as_str()), ("b", "c", "a"));
+/// ```
+#[macro_export]
+macro_rules! rotate3 {
+    ($a:expr, $b:expr, $c:expr $(,)?) => {
+        {
+            ::core::mem::swap(&mut $a, &mut $b);
+            ::core::mem::swap(&mut $b, &mut $c);
+        }
+    };
+}
+
+/// Set environment variables for the duration of a block
+///
+/// The previous values are restored by an `EnvGuard` when the block
+/// finishes, even if it panics. Returns the value of the block
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::scoped_env;
+///
+/// std::env::remove_var("MYTOOLS_EXAMPLE_LEVEL");
+/// let level = scoped_env!("MYTOOLS_EXAMPLE_LEVEL" => "debug", "MYTOOLS_EXAMPLE_COLOR" => "1", {
+///     std::env::var("MYTOOLS_EXAMPLE_LEVEL").unwrap()
+/// });
+/// assert_eq!(level, "debug");
+/// assert!(std::env::var("MYTOOLS_EXAMPLE_LEVEL").is_err());
+/// ```
+#[macro_export]
+macro_rules! scoped_env {
+    // Run the body once every variable is set
+    (@set $guard:ident; $body:block) => {
+        $body
+    };
+    (@set $guard:ident; $key:expr => $value:expr, $($rest:tt)+) => {
+        {
+            $guard.set_var($key, $value);
+            $crate::scoped_env!(@set $guard; $($rest)+)
+        }
+    };
+    ($($args:tt)+) => {
+        {
+            let mut guard = $crate::borrow::EnvGuard::new();
+            $crate::scoped_env!(@set guard; $($args)+)
+        }
+    };
+}
+
+/// Implement `Display` for a type from a single expression
+///
+/// The expression can be anything that implements `Display`, such as a
+/// `format!` call or a field
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::display_impl;
+///
+/// struct User { name: String, id: u32 }
+/// struct Meters(f64);
+///
+/// display_impl!(User, self => format!("{} ({})", self.name, self.id));
+/// display_impl!(Meters, self => format!("{:.1}m", self.0));
+///
+/// let user = User { name: "ada".to_string(), id: 7 };
+/// assert_eq!(user.to_string(), "ada (7)");
+/// assert_eq!(format!("{:>8}", Meters(2.54)), "    2.5m");
+/// ```
+#[macro_export]
+macro_rules! display_impl {
+    ($ty:ty, $this:ident => $body:expr $(,)?) => {
+        impl ::core::fmt::Display for $ty {
+            fn fmt(&$this, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Display::fmt(&$body, f)
+            }
+        }
+    };
+}
+
+/// Print an error and its chain of sources to stderr (used internally)
+#[doc(hidden)]
+pub fn report_main_error(err: Box<dyn std::error::Error + '_>) {
+    eprintln!("error: {}", err);
+    let mut source = err.source();
+    while let Some(cause) = source {
+        eprintln!("  caused by: {}", cause);
+        source = cause.source();
+    }
+}
+
+/// Generate a `main` function around a fallible entry point
+///
+/// `run` can return any error convertible to `Box<dyn Error>`, including
+/// `String`. On failure the error and its sources are printed to stderr and
+/// the process exits with `exit_code`, or 1 if none is given
+///
+/// # Examples
+///
+/// ```
+/// use rs_mytools::main_result;
+///
+/// fn run() -> Result<(), std::num::ParseIntError> {
+///     let workers: u32 = "4".parse()?;
+///     println!("starting {} workers", workers);
+///     Ok(())
+/// }
+///
+/// main_result!(run, exit_code = 2);
+/// ```
+#[macro_export]
+macro_rules! main_result {
+    ($run:path $(,)?) => {
+        $crate::main_result!($run, exit_code = 1);
+    };
+    ($run:path, exit_code = $code:expr $(,)?) => {
+        fn main() {
+            if let ::core::result::Result::Err(err) = $run() {
+                $crate::macros::report_main_error(::core::convert::Into::into(err));
+                ::std::process::exit($code);
+            }
+        }
+    };
+}
\ No newline at end of file
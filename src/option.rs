@@ -1,3 +1,9 @@
+use crate::result::{log_at, Error, LogLevel};
+use std::cell::{OnceCell, RefCell};
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::panic::Location;
+
 pub trait OptionExt<T> {
     /// Apply a function to the contained value if the option is `Some`, otherwise return `default`
     fn map_or_default<U, F>(self, default: U, f: F) -> U
@@ -9,6 +15,41 @@ pub trait OptionExt<T> {
     fn try_map<U, E, F>(self, f: F) -> Result<Option<U>, E>
     where
         F: FnOnce(T) -> Result<U, E>;
+
+    /// Run a side effect if the option is `None`, passing the option through unchanged
+    fn inspect_none<F>(self, f: F) -> Self
+    where
+        F: FnOnce();
+
+    /// Run a side effect on the contained value if the option is `Some`, passing the option through unchanged
+    fn tap_some<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&T);
+
+    /// Return the option if it is `Some`, otherwise fetch a replacement from a fallible source
+    fn or_else_try<E, F>(self, f: F) -> Result<Option<T>, E>
+    where
+        F: FnOnce() -> Result<Option<T>, E>;
+
+    /// Combine two options with a function, returning `Some` only when both are `Some`
+    fn zip_with<U, R, F>(self, other: Option<U>, f: F) -> Option<R>
+    where
+        F: FnOnce(T, U) -> R;
+
+    /// Pair two options that must be set together, failing when exactly one of them is `Some`
+    fn both_or_neither<U>(self, other: Option<U>) -> Result<Option<(T, U)>, String>;
+
+    /// Convert to a `Result`, building the error message lazily and recording the caller's location
+    fn ok_or_ctx<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C;
+
+    /// Return the contained value, or print `msg` with the caller's location to stderr and panic
+    fn expect_or_log(self, msg: &str) -> T;
+
+    /// Print `msg` with the caller's location to stderr if the option is `None`, passing it through unchanged
+    fn warn_none(self, msg: &str) -> Self;
 }
 
 impl<T> OptionExt<T> for Option<T> {
@@ -31,4 +72,405 @@ impl<T> OptionExt<T> for Option<T> {
             None => Ok(None),
         }
     }
+
+    fn inspect_none<F>(self, f: F) -> Self
+    where
+        F: FnOnce(),
+    {
+        if self.is_none() {
+            f();
+        }
+        self
+    }
+
+    fn tap_some<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&T),
+    {
+        if let Some(value) = &self {
+            f(value);
+        }
+        self
+    }
+
+    fn or_else_try<E, F>(self, f: F) -> Result<Option<T>, E>
+    where
+        F: FnOnce() -> Result<Option<T>, E>,
+    {
+        match self {
+            Some(value) => Ok(Some(value)),
+            None => f(),
+        }
+    }
+
+    fn zip_with<U, R, F>(self, other: Option<U>, f: F) -> Option<R>
+    where
+        F: FnOnce(T, U) -> R,
+    {
+        match (self, other) {
+            (Some(a), Some(b)) => Some(f(a, b)),
+            _ => None,
+        }
+    }
+
+    fn both_or_neither<U>(self, other: Option<U>) -> Result<Option<(T, U)>, String> {
+        match (self, other) {
+            (Some(a), Some(b)) => Ok(Some((a, b))),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err("expected both values or neither, but only the first was set".to_string()),
+            (None, Some(_)) => Err("expected both values or neither, but only the second was set".to_string()),
+        }
+    }
+
+    #[track_caller]
+    fn ok_or_ctx<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+    {
+        let location = Location::caller();
+        match self {
+            Some(value) => Ok(value),
+            None => Err(Error::at(f(), location)),
+        }
+    }
+
+    #[track_caller]
+    fn expect_or_log(self, msg: &str) -> T {
+        match self {
+            Some(value) => value,
+            None => {
+                log_at(LogLevel::Error, Location::caller(), &msg);
+                panic!("{}", msg)
+            }
+        }
+    }
+
+    #[track_caller]
+    fn warn_none(self, msg: &str) -> Self {
+        if self.is_none() {
+            log_at(LogLevel::Warn, Location::caller(), &msg);
+        }
+        self
+    }
+}
+
+pub trait OptionMutExt<T> {
+    /// Return the contained value, inserting one from a fallible constructor if the option is `None`
+    fn get_or_try_insert_with<E, F>(&mut self, f: F) -> Result<&mut T, E>
+    where
+        F: FnOnce() -> Result<T, E>;
+
+    /// Insert `value` only if the option is `None`, returning true if it was inserted
+    fn insert_if_none(&mut self, value: T) -> bool;
+
+    /// Take the contained value only if it satisfies a predicate, otherwise leave it in place
+    ///
+    /// Named to avoid clashing with the inherent `Option::take_if` and the blanket
+    /// `TakeReplace::take_if`; prefer std's `Option::take_if` when the predicate needs `&mut T`.
+    fn take_some_if<P>(&mut self, pred: P) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool;
+
+    /// Replace the contained value with `value` only if it satisfies a predicate, returning the old value
+    ///
+    /// Named to avoid clashing with the blanket `TakeReplace::replace_if`.
+    fn replace_some_if<P>(&mut self, pred: P, value: T) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool;
+}
+
+impl<T> OptionMutExt<T> for Option<T> {
+    fn get_or_try_insert_with<E, F>(&mut self, f: F) -> Result<&mut T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if self.is_none() {
+            *self = Some(f()?);
+        }
+        Ok(self.as_mut().expect("option was just filled"))
+    }
+
+    fn insert_if_none(&mut self, value: T) -> bool {
+        if self.is_some() {
+            return false;
+        }
+        *self = Some(value);
+        true
+    }
+
+    fn take_some_if<P>(&mut self, pred: P) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool,
+    {
+        if self.as_ref().is_some_and(pred) {
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    fn replace_some_if<P>(&mut self, pred: P, value: T) -> Option<T>
+    where
+        P: FnOnce(&T) -> bool,
+    {
+        if self.as_ref().is_some_and(pred) {
+            self.replace(value)
+        } else {
+            None
+        }
+    }
+}
+
+/// A struct field whose value is computed on first `get` and may never be computed at all
+///
+/// Unlike `Lazy` this is single-threaded and the initializer is `FnMut`, so
+/// after `take` the next `get` computes a fresh value
+pub struct LazyOption<T, F = fn() -> T> {
+    value: OnceCell<T>,
+    init: RefCell<F>,
+}
+
+impl<T, F: FnMut() -> T> LazyOption<T, F> {
+    /// Create a value that will be computed by `init` when first needed
+    pub const fn new(init: F) -> Self {
+        Self {
+            value: OnceCell::new(),
+            init: RefCell::new(init),
+        }
+    }
+
+    /// Compute the value if needed and return a reference to it
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| (self.init.borrow_mut())())
+    }
+
+    /// Compute the value if needed and return a mutable reference to it
+    pub fn get_mut(&mut self) -> &mut T {
+        self.get();
+        self.value.get_mut().expect("value was just computed")
+    }
+}
+
+impl<T, F> LazyOption<T, F> {
+    /// Get the value without computing it
+    pub fn peek(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Take the computed value out, leaving the field uncomputed
+    pub fn take(&mut self) -> Option<T> {
+        self.value.take()
+    }
+
+    /// Returns true if the value has been computed and not taken
+    pub fn is_computed(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for LazyOption<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.peek() {
+            Some(value) => f.debug_tuple("LazyOption").field(value).finish(),
+            None => f.write_str("LazyOption(<uncomputed>)"),
+        }
+    }
+}
+
+/// A value of one of two types
+///
+/// Iterator, `Read`, `BufRead` and `Write` impls forward to whichever side is
+/// present when both sides implement the trait
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Returns true if this is a `Left`
+    pub fn is_left(&self) -> bool {
+        matches!(self, Either::Left(_))
+    }
+
+    /// Returns true if this is a `Right`
+    pub fn is_right(&self) -> bool {
+        matches!(self, Either::Right(_))
+    }
+
+    /// The left value, if any
+    pub fn left(self) -> Option<L> {
+        match self {
+            Either::Left(left) => Some(left),
+            Either::Right(_) => None,
+        }
+    }
+
+    /// The right value, if any
+    pub fn right(self) -> Option<R> {
+        match self {
+            Either::Left(_) => None,
+            Either::Right(right) => Some(right),
+        }
+    }
+
+    /// Borrow the contents
+    pub fn as_ref(&self) -> Either<&L, &R> {
+        match self {
+            Either::Left(left) => Either::Left(left),
+            Either::Right(right) => Either::Right(right),
+        }
+    }
+
+    /// Mutably borrow the contents
+    pub fn as_mut(&mut self) -> Either<&mut L, &mut R> {
+        match self {
+            Either::Left(left) => Either::Left(left),
+            Either::Right(right) => Either::Right(right),
+        }
+    }
+
+    /// Swap the sides
+    pub fn flip(self) -> Either<R, L> {
+        match self {
+            Either::Left(left) => Either::Right(left),
+            Either::Right(right) => Either::Left(right),
+        }
+    }
+
+    /// Apply a function to the left value, leaving a right value unchanged
+    pub fn map_left<M, F>(self, f: F) -> Either<M, R>
+    where
+        F: FnOnce(L) -> M,
+    {
+        match self {
+            Either::Left(left) => Either::Left(f(left)),
+            Either::Right(right) => Either::Right(right),
+        }
+    }
+
+    /// Apply a function to the right value, leaving a left value unchanged
+    pub fn map_right<S, F>(self, f: F) -> Either<L, S>
+    where
+        F: FnOnce(R) -> S,
+    {
+        match self {
+            Either::Left(left) => Either::Left(left),
+            Either::Right(right) => Either::Right(f(right)),
+        }
+    }
+
+    /// Collapse to a single value by applying `f` to a left value or `g` to a right value
+    pub fn either<U, F, G>(self, f: F, g: G) -> U
+    where
+        F: FnOnce(L) -> U,
+        G: FnOnce(R) -> U,
+    {
+        match self {
+            Either::Left(left) => f(left),
+            Either::Right(right) => g(right),
+        }
+    }
+}
+
+impl<T> Either<T, T> {
+    /// Extract the value when both sides have the same type
+    pub fn into_inner(self) -> T {
+        match self {
+            Either::Left(value) | Either::Right(value) => value,
+        }
+    }
+}
+
+impl<L: fmt::Display, R: fmt::Display> fmt::Display for Either<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Either::Left(left) => left.fmt(f),
+            Either::Right(right) => right.fmt(f),
+        }
+    }
+}
+
+impl<L, R> Iterator for Either<L, R>
+where
+    L: Iterator,
+    R: Iterator<Item = L::Item>,
+{
+    type Item = L::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Either::Left(left) => left.next(),
+            Either::Right(right) => right.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Either::Left(left) => left.size_hint(),
+            Either::Right(right) => right.size_hint(),
+        }
+    }
+}
+
+impl<L, R> DoubleEndedIterator for Either<L, R>
+where
+    L: DoubleEndedIterator,
+    R: DoubleEndedIterator<Item = L::Item>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Either::Left(left) => left.next_back(),
+            Either::Right(right) => right.next_back(),
+        }
+    }
+}
+
+impl<L, R> ExactSizeIterator for Either<L, R>
+where
+    L: ExactSizeIterator,
+    R: ExactSizeIterator<Item = L::Item>,
+{
+}
+
+impl<L: Read, R: Read> Read for Either<L, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Either::Left(left) => left.read(buf),
+            Either::Right(right) => right.read(buf),
+        }
+    }
+}
+
+impl<L: BufRead, R: BufRead> BufRead for Either<L, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Either::Left(left) => left.fill_buf(),
+            Either::Right(right) => right.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            Either::Left(left) => left.consume(amount),
+            Either::Right(right) => right.consume(amount),
+        }
+    }
+}
+
+impl<L: Write, R: Write> Write for Either<L, R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Either::Left(left) => left.write(buf),
+            Either::Right(right) => right.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Either::Left(left) => left.flush(),
+            Either::Right(right) => right.flush(),
+        }
+    }
 }
@@ -1,3 +1,17 @@
+use crate::random::Rng;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::panic::Location;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A boxed error that can be sent between threads
+pub type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
 pub trait ResultExt<T, E> {
     /// Ignore the error case and convert to Option
     fn ignore_err(self) -> Option<T>;
@@ -6,14 +20,79 @@ pub trait ResultExt<T, E> {
     fn map_err_with<F>(self, f: F) -> Result<T, E>
     where
         F: FnOnce(&E) -> E;
+
+    /// Wrap the error in an `Error` with a context message, recording the caller's location
+    fn context<C>(self, message: C) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        E: Into<BoxError>;
+
+    /// Like `context`, but only builds the message if the result is an error
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+        E: Into<BoxError>;
+
+    /// Run a side effect on the value if the result is `Ok`, passing the result through unchanged
+    fn tap_ok<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&T);
+
+    /// Run a side effect on the error if the result is `Err`, passing the result through unchanged
+    fn tap_err<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&E);
+
+    /// If the result is an error, call `recompute` up to `n` more times until it succeeds, returning the last error otherwise
+    fn or_retry_n<F>(self, n: usize, recompute: F) -> Self
+    where
+        F: FnMut() -> Result<T, E>;
+
+    /// Print the error with the caller's location to stderr at `level` and convert to Option
+    fn log_err(self, level: LogLevel) -> Option<T>
+    where
+        E: fmt::Display;
+
+    /// Print the error as a warning with the caller's location to stderr and convert to Option
+    fn warn_err(self) -> Option<T>
+    where
+        E: fmt::Display;
+
+    /// Convert to Option, passing the error to `f` first so it is not silently dropped
+    fn ok_or_warn<F>(self, f: F) -> Option<T>
+    where
+        F: FnOnce(&E);
+
+    /// Convert the error into an `Error` and record the caller's location in its trace
+    fn err_here(self) -> Result<T, Error>
+    where
+        E: Into<BoxError>;
+
+    /// Collapse a `Result<Result<U, E2>, E>` into a `Result<U, E>`, converting the inner error
+    fn flatten_nested(self) -> Result<T::Ok, E>
+    where
+        T: NestedResult,
+        T::Err: Into<E>;
+
+    /// Convert the error into a `StrError` holding its message
+    fn map_err_str(self) -> Result<T, StrError>
+    where
+        E: fmt::Display;
+
+    /// Swap the `Ok` and `Err` variants
+    fn invert(self) -> Result<E, T>;
+
+    /// Return the error, or panic with a message built from the unexpected `Ok` value
+    fn expect_err_with<M, F>(self, f: F) -> E
+    where
+        M: fmt::Display,
+        F: FnOnce(&T) -> M;
 }
 
 impl<T, E> ResultExt<T, E> for Result<T, E> {
     fn ignore_err(self) -> Option<T> {
-        match self {
-            Ok(value) => Some(value),
-            Err(_) => None,
-        }
+        self.ok()
     }
     
     fn map_err_with<F>(self, f: F) -> Result<T, E>
@@ -25,4 +104,1086 @@ impl<T, E> ResultExt<T, E> for Result<T, E> {
             Err(e) => Err(f(&e)),
         }
     }
+
+    #[track_caller]
+    fn context<C>(self, message: C) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        E: Into<BoxError>,
+    {
+        let location = Location::caller();
+        self.map_err(|err| Error::at(message, location).with_source(err))
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+        E: Into<BoxError>,
+    {
+        let location = Location::caller();
+        self.map_err(|err| Error::at(f(), location).with_source(err))
+    }
+
+    fn tap_ok<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&T),
+    {
+        if let Ok(value) = &self {
+            f(value);
+        }
+        self
+    }
+
+    fn tap_err<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&E),
+    {
+        if let Err(e) = &self {
+            f(e);
+        }
+        self
+    }
+
+    fn or_retry_n<F>(self, n: usize, mut recompute: F) -> Self
+    where
+        F: FnMut() -> Result<T, E>,
+    {
+        let mut result = self;
+        for _ in 0..n {
+            if result.is_ok() {
+                break;
+            }
+            result = recompute();
+        }
+        result
+    }
+
+    #[track_caller]
+    fn log_err(self, level: LogLevel) -> Option<T>
+    where
+        E: fmt::Display,
+    {
+        let location = Location::caller();
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log_at(level, location, &e);
+                None
+            }
+        }
+    }
+
+    #[track_caller]
+    fn warn_err(self) -> Option<T>
+    where
+        E: fmt::Display,
+    {
+        let location = Location::caller();
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log_at(LogLevel::Warn, location, &e);
+                None
+            }
+        }
+    }
+
+    fn ok_or_warn<F>(self, f: F) -> Option<T>
+    where
+        F: FnOnce(&E),
+    {
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                f(&e);
+                None
+            }
+        }
+    }
+
+    #[track_caller]
+    fn err_here(self) -> Result<T, Error>
+    where
+        E: Into<BoxError>,
+    {
+        let location = Location::caller();
+        self.map_err(|err| Error::traced(err.into(), location))
+    }
+
+    fn flatten_nested(self) -> Result<T::Ok, E>
+    where
+        T: NestedResult,
+        T::Err: Into<E>,
+    {
+        match self {
+            Ok(inner) => inner.into_result().map_err(Into::into),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn map_err_str(self) -> Result<T, StrError>
+    where
+        E: fmt::Display,
+    {
+        self.map_err(|e| StrError(e.to_string()))
+    }
+
+    fn invert(self) -> Result<E, T> {
+        match self {
+            Ok(value) => Err(value),
+            Err(e) => Ok(e),
+        }
+    }
+
+    #[track_caller]
+    fn expect_err_with<M, F>(self, f: F) -> E
+    where
+        M: fmt::Display,
+        F: FnOnce(&T) -> M,
+    {
+        match self {
+            Ok(value) => panic!("{}", f(&value)),
+            Err(e) => e,
+        }
+    }
+}
+
+/// A `Result` nested inside another, for `ResultExt::flatten_nested` (used internally)
+#[doc(hidden)]
+pub trait NestedResult {
+    type Ok;
+    type Err;
+
+    fn into_result(self) -> Result<Self::Ok, Self::Err>;
+}
+
+impl<T, E> NestedResult for Result<T, E> {
+    type Ok = T;
+    type Err = E;
+
+    fn into_result(self) -> Result<T, E> {
+        self
+    }
+}
+
+/// Severity of a message printed by `ResultExt::log_err`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warning",
+            LogLevel::Error => "error",
+        })
+    }
+}
+
+/// Print `[file:line] level: message` to stderr
+pub(crate) fn log_at(level: LogLevel, location: &Location<'_>, message: &dyn fmt::Display) {
+    eprintln!("[{}:{}] {}: {}", location.file(), location.line(), level, message);
+}
+
+/// An error carrying a message, the source location it was created at and
+/// an optional source error
+///
+/// Built with `Error::new`, `OptionExt::ok_or_ctx`, `ResultExt::context` or
+/// `ResultExt::err_here`. Display shows the outermost message, or the whole
+/// chain joined by `: ` with `{:#}`, followed by one `at` line per
+/// `err_here` hop; Debug shows the chain with locations
+pub struct Error {
+    message: String,
+    location: &'static Location<'static>,
+    source: Option<BoxError>,
+    /// The message was copied from the source, which is then left out of the chain
+    transparent: bool,
+    /// Locations recorded by `err_here`, innermost first
+    trace: Vec<&'static Location<'static>>,
+}
+
+impl Error {
+    /// Create an error from a message, recording the caller's location
+    #[track_caller]
+    pub fn new(message: impl fmt::Display) -> Self {
+        Self::at(message, Location::caller())
+    }
+
+    /// Create an error from a message and an explicit location
+    pub fn at(message: impl fmt::Display, location: &'static Location<'static>) -> Self {
+        Self {
+            message: message.to_string(),
+            location,
+            source: None,
+            transparent: false,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Add an `err_here` hop, wrapping errors of other types without changing their message
+    fn traced(err: BoxError, location: &'static Location<'static>) -> Self {
+        match err.downcast::<Error>() {
+            Ok(err) => {
+                let mut err = *err;
+                err.trace.push(location);
+                err
+            }
+            Err(err) => Self {
+                message: err.to_string(),
+                location,
+                source: Some(err),
+                transparent: true,
+                trace: vec![location],
+            },
+        }
+    }
+
+    /// Attach the error that caused this one
+    pub fn with_source(mut self, source: impl Into<BoxError>) -> Self {
+        self.source = Some(source.into());
+        self.transparent = false;
+        self
+    }
+
+    /// The error message, without the messages of its sources
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the error was created
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The locations recorded by `err_here`, innermost first
+    pub fn trace(&self) -> &[&'static Location<'static>] {
+        &self.trace
+    }
+
+    /// Iterate over this error and then each of its sources in turn
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+        std::iter::successors(Some(self as &(dyn StdError + 'static)), |&err| err.source())
+    }
+
+    /// The innermost error in the chain
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.chain().last().expect("chain always contains self")
+    }
+
+    /// Find an error of type `E` in the chain, including one wrapped by `err_here`
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        let wrapped = self.source.as_deref().filter(|_| self.transparent);
+        wrapped
+            .and_then(|err| err.downcast_ref::<E>())
+            .or_else(|| self.chain().find_map(|err| err.downcast_ref::<E>()))
+    }
+
+    /// The message with its location, or with its `err_here` trace if it has one
+    fn write_entry(&self, f: &mut fmt::Formatter<'_>, indent: &str) -> fmt::Result {
+        if self.trace.is_empty() {
+            write!(f, "{} (at {})", self.message, self.location)
+        } else {
+            f.write_str(&self.message)?;
+            self.write_trace(f, indent)
+        }
+    }
+
+    fn write_trace(&self, f: &mut fmt::Formatter<'_>, indent: &str) -> fmt::Result {
+        for hop in &self.trace {
+            write!(f, "\n{}  at {}", indent, hop)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if f.alternate() {
+            for cause in self.chain().skip(1) {
+                write!(f, ": {}", cause)?;
+            }
+        }
+        self.write_trace(f, "")
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_entry(f, "")?;
+        let mut source = self.source();
+        if source.is_some() {
+            f.write_str("\n\nCaused by:")?;
+        }
+        while let Some(cause) = source {
+            match cause.downcast_ref::<Error>() {
+                Some(err) => {
+                    f.write_str("\n    ")?;
+                    err.write_entry(f, "    ")?;
+                }
+                None => write!(f, "\n    {}", cause)?,
+            }
+            source = cause.source();
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        let source = self.source.as_deref().map(|err| err as &(dyn StdError + 'static));
+        if self.transparent {
+            source.and_then(StdError::source)
+        } else {
+            source
+        }
+    }
+}
+
+/// Fail with an `Error` carrying `message` unless `condition` holds
+///
+/// The function form of `ensure!`, for closures and iterator chains
+#[track_caller]
+pub fn ensure(condition: bool, message: impl fmt::Display) -> Result<(), Error> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Error::new(message))
+    }
+}
+
+/// Fail with an `Error` carrying `message`
+#[track_caller]
+pub fn bail<T>(message: impl fmt::Display) -> Result<T, Error> {
+    Err(Error::new(message))
+}
+
+/// Run a fallible operation and measure how long it took, whether it succeeded or not
+pub fn timed<T, E, F>(f: F) -> (Result<T, E>, Duration)
+where
+    F: FnOnce() -> Result<T, E>,
+{
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// Like `timed`, but only reports the elapsed time on success so the result chains with `?`
+pub fn with_timing<T, E, F>(f: F) -> Result<(T, Duration), E>
+where
+    F: FnOnce() -> Result<T, E>,
+{
+    let (result, elapsed) = timed(f);
+    result.map(|value| (value, elapsed))
+}
+
+/// An error that is just a message
+///
+/// Converts from `&str` and `String`, so functions that build their errors
+/// as strings can still return a type that works with `?` and `Box<dyn Error>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StrError(String);
+
+impl StrError {
+    /// Create an error from a message
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+
+    /// The error message
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Unwrap into the message
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl From<&str> for StrError {
+    fn from(message: &str) -> Self {
+        Self(message.to_string())
+    }
+}
+
+impl From<String> for StrError {
+    fn from(message: String) -> Self {
+        Self(message)
+    }
+}
+
+impl From<StrError> for String {
+    fn from(err: StrError) -> Self {
+        err.0
+    }
+}
+
+impl fmt::Display for StrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for StrError {}
+
+/// A `Result` with an `AnyError`
+pub type AnyResult<T> = Result<T, AnyError>;
+
+/// A type-erased error for application code that has no dedicated error enum
+///
+/// Any `Error + Send + Sync` converts into it with `?`. A backtrace is
+/// captured on creation when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is set.
+/// Like `Box<dyn Error>` it does not implement `Error` itself, which is what
+/// allows the blanket `From` impl
+pub struct AnyError {
+    inner: BoxError,
+    backtrace: Backtrace,
+}
+
+impl AnyError {
+    /// Wrap an error
+    pub fn new<E>(err: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Self::from_boxed(Box::new(err))
+    }
+
+    /// Create an error from a message
+    pub fn msg(message: impl fmt::Display) -> Self {
+        Self::new(StrError(message.to_string()))
+    }
+
+    /// Wrap an already boxed error
+    pub fn from_boxed(inner: BoxError) -> Self {
+        Self {
+            inner,
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Returns true if the wrapped error is of type `E`
+    pub fn is<E: StdError + 'static>(&self) -> bool {
+        self.inner.is::<E>()
+    }
+
+    /// Borrow the wrapped error as `E` if it has that type
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        self.inner.downcast_ref()
+    }
+
+    /// Mutably borrow the wrapped error as `E` if it has that type
+    pub fn downcast_mut<E: StdError + 'static>(&mut self) -> Option<&mut E> {
+        self.inner.downcast_mut()
+    }
+
+    /// Take the wrapped error out as `E`, or give the error back if it has another type
+    pub fn downcast<E: StdError + 'static>(self) -> Result<E, Self> {
+        match self.inner.downcast() {
+            Ok(err) => Ok(*err),
+            Err(inner) => Err(Self {
+                inner,
+                backtrace: self.backtrace,
+            }),
+        }
+    }
+
+    /// The backtrace captured when the error was created
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+
+    /// The wrapped error
+    pub fn get_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        &*self.inner
+    }
+
+    /// Unwrap into the boxed error, dropping the backtrace
+    pub fn into_inner(self) -> BoxError {
+        self.inner
+    }
+}
+
+impl<E> From<E> for AnyError
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn from(err: E) -> Self {
+        Self::new(err)
+    }
+}
+
+impl From<AnyError> for BoxError {
+    fn from(err: AnyError) -> Self {
+        err.inner
+    }
+}
+
+impl From<AnyError> for Box<dyn StdError + 'static> {
+    fn from(err: AnyError) -> Self {
+        err.inner
+    }
+}
+
+impl fmt::Display for AnyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner)?;
+        if f.alternate() {
+            let mut source = self.inner.source();
+            while let Some(cause) = source {
+                write!(f, ": {}", cause)?;
+                source = cause.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AnyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the locations recorded by a wrapped `Error`
+        if let Some(err) = self.downcast_ref::<Error>() {
+            fmt::Debug::fmt(err, f)?;
+        } else {
+            write!(f, "{}", self.inner)?;
+            let mut source = self.inner.source();
+            if source.is_some() {
+                f.write_str("\n\nCaused by:")?;
+            }
+            while let Some(cause) = source {
+                write!(f, "\n    {}", cause)?;
+                source = cause.source();
+            }
+        }
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n\nStack backtrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// How `retry` schedules attempts of a fallible operation
+///
+/// Defaults to no delay between attempts and retrying every error. The delay
+/// is multiplied by the backoff factor after each failure, capped at
+/// `max_delay`, and then randomized by up to `jitter` of its length
+pub struct RetryPolicy<E> {
+    max_attempts: usize,
+    delay: Duration,
+    factor: f64,
+    max_delay: Option<Duration>,
+    jitter: f64,
+    should_retry: Box<dyn Fn(&E) -> bool + Send + Sync>,
+}
+
+impl<E> RetryPolicy<E> {
+    /// Create a policy that makes at most `max_attempts` attempts in total
+    pub fn new(max_attempts: usize) -> Self {
+        assert!(max_attempts > 0, "max_attempts must be greater than 0");
+        Self {
+            max_attempts,
+            delay: Duration::ZERO,
+            factor: 1.0,
+            max_delay: None,
+            jitter: 0.0,
+            should_retry: Box::new(|_| true),
+        }
+    }
+
+    /// Wait `delay` between attempts
+    pub fn fixed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self.factor = 1.0;
+        self
+    }
+
+    /// Wait `initial` before the second attempt and multiply the delay by `factor` after each failure
+    pub fn exponential(mut self, initial: Duration, factor: f64) -> Self {
+        assert!(factor.is_finite() && factor >= 1.0, "backoff factor must be finite and at least 1");
+        self.delay = initial;
+        self.factor = factor;
+        self
+    }
+
+    /// Never wait longer than `max_delay` between attempts
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Randomize each delay by up to `fraction` of its length in either direction
+    pub fn jitter(mut self, fraction: f64) -> Self {
+        assert!((0.0..=1.0).contains(&fraction), "jitter must be between 0 and 1");
+        self.jitter = fraction;
+        self
+    }
+
+    /// Only retry errors for which `pred` returns true, failing immediately on the others
+    pub fn retry_if<P>(mut self, pred: P) -> Self
+    where
+        P: Fn(&E) -> bool + Send + Sync + 'static,
+    {
+        self.should_retry = Box::new(pred);
+        self
+    }
+
+    /// The maximum number of attempts, including the first
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// The delay before attempt `attempt + 1`, without jitter
+    pub fn delay_after(&self, attempt: usize) -> Duration {
+        if self.delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let delay = self.delay.as_secs_f64() * self.factor.powi(exponent);
+        // Saturate once the delay outgrows `Duration`
+        let delay = Duration::try_from_secs_f64(delay).unwrap_or(Duration::MAX);
+        match self.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        }
+    }
+}
+
+impl<E> fmt::Debug for RetryPolicy<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("delay", &self.delay)
+            .field("factor", &self.factor)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Call `f` until it succeeds, the policy rejects the error or the attempts run out
+///
+/// Returns the first `Ok`, or the last `Err`. The function counterpart of the
+/// `retry!` macro, for library code and closures
+pub fn retry<T, E, F>(policy: &RetryPolicy<E>, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    let mut rng = Rng::new();
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= policy.max_attempts || !(policy.should_retry)(&err) => return Err(err),
+            Err(_) => {
+                let delay = policy.delay_after(attempt);
+                let spread = 1.0 + policy.jitter * (2.0 * rng.next_f64() - 1.0);
+                let jittered = delay.as_secs_f64() * spread;
+                thread::sleep(Duration::try_from_secs_f64(jittered).unwrap_or(Duration::MAX));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// A collection of errors, for validation passes and batch jobs that report everything that went wrong
+///
+/// Displays each error on its own line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiError<E> {
+    errors: Vec<E>,
+}
+
+impl<E> MultiError<E> {
+    /// Create an empty collection
+    pub fn new() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    /// Add an error
+    pub fn push(&mut self, err: E) {
+        self.errors.push(err);
+    }
+
+    /// Record the error of a result, returning its value if it was Ok
+    pub fn check<T>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push(err);
+                None
+            }
+        }
+    }
+
+    /// The number of errors collected
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns true if no errors were collected
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Iterate over the errors in the order they were added
+    pub fn iter(&self) -> std::slice::Iter<'_, E> {
+        self.errors.iter()
+    }
+
+    /// `Ok(value)` if no errors were collected, otherwise `Err(self)`
+    pub fn into_result<T>(self, value: T) -> Result<T, Self> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Unwrap into the collected errors
+    pub fn into_vec(self) -> Vec<E> {
+        self.errors
+    }
+}
+
+impl<E> Default for MultiError<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> From<Vec<E>> for MultiError<E> {
+    fn from(errors: Vec<E>) -> Self {
+        Self { errors }
+    }
+}
+
+impl<E> FromIterator<E> for MultiError<E> {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        Self {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<E> Extend<E> for MultiError<E> {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, iter: I) {
+        self.errors.extend(iter);
+    }
+}
+
+impl<E> IntoIterator for MultiError<E> {
+    type Item = E;
+    type IntoIter = std::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a, E> IntoIterator for &'a MultiError<E> {
+    type Item = &'a E;
+    type IntoIter = std::slice::Iter<'a, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for MultiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> StdError for MultiError<E> {}
+
+/// A result that keeps every error when combined, for reporting all validation problems at once
+///
+/// Combining two `Invalid` values with `and` merges their errors instead of
+/// stopping at the first. Convert to a `Result` with `into_result` at the end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validated<T, E> {
+    Valid(T),
+    Invalid(MultiError<E>),
+}
+
+impl<T, E> Validated<T, E> {
+    /// A failed validation with a single error
+    pub fn invalid(err: E) -> Self {
+        Validated::Invalid(MultiError::from(vec![err]))
+    }
+
+    /// Returns true if there are no errors
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Validated::Valid(_))
+    }
+
+    /// Combine two validations with `f`, collecting the errors of both if either failed
+    pub fn and<A, B, F>(a: Validated<A, E>, b: Validated<B, E>, f: F) -> Self
+    where
+        F: FnOnce(A, B) -> T,
+    {
+        match (a, b) {
+            (Validated::Valid(a), Validated::Valid(b)) => Validated::Valid(f(a, b)),
+            (Validated::Invalid(errors), Validated::Valid(_))
+            | (Validated::Valid(_), Validated::Invalid(errors)) => Validated::Invalid(errors),
+            (Validated::Invalid(mut errors), Validated::Invalid(more)) => {
+                errors.extend(more);
+                Validated::Invalid(errors)
+            }
+        }
+    }
+
+    /// Apply a function to the value if there are no errors
+    pub fn map<U, F>(self, f: F) -> Validated<U, E>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Validated::Valid(value) => Validated::Valid(f(value)),
+            Validated::Invalid(errors) => Validated::Invalid(errors),
+        }
+    }
+
+    /// The errors collected so far, if any
+    pub fn errors(&self) -> Option<&MultiError<E>> {
+        match self {
+            Validated::Valid(_) => None,
+            Validated::Invalid(errors) => Some(errors),
+        }
+    }
+
+    /// Convert to a `Result` carrying every error
+    pub fn into_result(self) -> Result<T, MultiError<E>> {
+        match self {
+            Validated::Valid(value) => Ok(value),
+            Validated::Invalid(errors) => Err(errors),
+        }
+    }
+}
+
+impl<T, E> From<Result<T, E>> for Validated<T, E> {
+    fn from(result: Result<T, E>) -> Self {
+        match result {
+            Ok(value) => Validated::Valid(value),
+            Err(err) => Validated::invalid(err),
+        }
+    }
+}
+
+impl<T, E> From<Validated<T, E>> for Result<T, MultiError<E>> {
+    fn from(validated: Validated<T, E>) -> Self {
+        validated.into_result()
+    }
+}
+
+impl<T, E> FromIterator<Validated<T, E>> for Validated<Vec<T>, E> {
+    fn from_iter<I: IntoIterator<Item = Validated<T, E>>>(iter: I) -> Self {
+        let mut values = Vec::new();
+        let mut errors = MultiError::new();
+        for item in iter {
+            match item {
+                Validated::Valid(value) => values.push(value),
+                Validated::Invalid(more) => errors.extend(more),
+            }
+        }
+        if errors.is_empty() {
+            Validated::Valid(values)
+        } else {
+            Validated::Invalid(errors)
+        }
+    }
+}
+
+impl<T, E> FromIterator<Result<T, E>> for Validated<Vec<T>, E> {
+    fn from_iter<I: IntoIterator<Item = Result<T, E>>>(iter: I) -> Self {
+        iter.into_iter().map(Validated::from).collect()
+    }
+}
+
+/// An error that knows which process exit code it should produce
+///
+/// Implement it for an application's error enum to give `run_main` distinct
+/// exit codes. The impls provided here use 1, except `MultiError` which
+/// uses the code of its first error
+pub trait ExitCodeError {
+    /// The code to exit the process with when this error reaches `main`
+    fn exit_code(&self) -> i32;
+}
+
+impl ExitCodeError for Error {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for AnyError {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for BoxError {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for io::Error {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for String {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for &str {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl ExitCodeError for StrError {
+    fn exit_code(&self) -> i32 {
+        1
+    }
+}
+
+impl<E: ExitCodeError> ExitCodeError for MultiError<E> {
+    fn exit_code(&self) -> i32 {
+        self.iter().next().map_or(1, ExitCodeError::exit_code)
+    }
+}
+
+/// Run an application's entry point and exit the process with its outcome
+///
+/// On success the process exits with 0. On failure the error and its chain
+/// of sources are printed to stderr and the process exits with the error's
+/// `exit_code`
+pub fn run_main<E, F>(f: F) -> !
+where
+    F: FnOnce() -> Result<(), E>,
+    E: ExitCodeError + Into<Box<dyn StdError>>,
+{
+    match f() {
+        Ok(()) => process::exit(0),
+        Err(err) => {
+            let code = err.exit_code();
+            crate::macros::report_main_error(err.into());
+            process::exit(code)
+        }
+    }
+}
+
+/// Runs fallible cleanup actions when it goes out of scope
+///
+/// Actions run in reverse registration order and all of them run even if
+/// some fail. Call `finish` or `finish_with` to get the failures back as a
+/// `MultiError`; if the guard is simply dropped they are printed to stderr
+/// with the location the guard was created at
+pub struct CleanupGuard<'a, E: fmt::Display = BoxError> {
+    actions: Vec<Box<dyn FnOnce() -> Result<(), E> + 'a>>,
+    location: &'static Location<'static>,
+}
+
+impl<'a> CleanupGuard<'a> {
+    /// Create a guard whose actions may fail with any error type
+    #[track_caller]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, E: fmt::Display> CleanupGuard<'a, E> {
+    /// Register a cleanup action
+    pub fn defer_try<F, E2>(&mut self, f: F)
+    where
+        F: FnOnce() -> Result<(), E2> + 'a,
+        E2: Into<E>,
+    {
+        self.actions.push(Box::new(move || f().map_err(Into::into)));
+    }
+
+    /// The number of actions that have not run yet
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Returns true if there are no pending actions
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Run every action now, returning the failures if there were any
+    pub fn finish(mut self) -> Result<(), MultiError<E>> {
+        self.run_all().into_result(())
+    }
+
+    /// Run every action now and combine their failures with the outcome of the guarded work
+    ///
+    /// A primary error stays first in the returned `MultiError`, so cleanup
+    /// failures never hide it
+    pub fn finish_with<T>(mut self, result: Result<T, E>) -> Result<T, MultiError<E>> {
+        let mut errors = MultiError::new();
+        let value = errors.check(result);
+        errors.extend(self.run_all());
+        match value {
+            Some(value) => errors.into_result(value),
+            None => Err(errors),
+        }
+    }
+
+    fn run_all(&mut self) -> MultiError<E> {
+        mem::take(&mut self.actions)
+            .into_iter()
+            .rev()
+            .filter_map(|action| action().err())
+            .collect()
+    }
+}
+
+impl<'a, E: fmt::Display> Default for CleanupGuard<'a, E> {
+    #[track_caller]
+    fn default() -> Self {
+        Self {
+            actions: Vec::new(),
+            location: Location::caller(),
+        }
+    }
+}
+
+impl<'a, E: fmt::Display> fmt::Debug for CleanupGuard<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CleanupGuard")
+            .field("pending", &self.actions.len())
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl<'a, E: fmt::Display> Drop for CleanupGuard<'a, E> {
+    fn drop(&mut self) {
+        for err in self.run_all() {
+            log_at(LogLevel::Error, self.location, &format_args!("cleanup failed: {}", err));
+        }
+    }
 }
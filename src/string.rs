@@ -1,3 +1,5 @@
+use std::fmt;
+
 pub trait StringExt {
     /// Check if a string is empty or only contains whitespace
     fn is_blank(&self) -> bool;
@@ -39,3 +41,146 @@ impl StringExt for str {
             .collect()
     }
 }
+
+/// A compiled wildcard pattern
+///
+/// Supports `*` (any run of characters), `?` (any single character),
+/// character classes such as `[abc]`, `[a-z]` and `[!0-9]`, and `\` to
+/// escape the next character. Patterns must match the whole input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wildcard {
+    source: String,
+    tokens: Vec<WildcardToken>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WildcardToken {
+    Literal(char),
+    AnyChar,
+    AnyRun,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl WildcardToken {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            WildcardToken::Literal(literal) => *literal == c,
+            WildcardToken::AnyChar => true,
+            WildcardToken::AnyRun => false,
+            WildcardToken::Class { negated, ranges } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+}
+
+impl Wildcard {
+    /// Compile a pattern, failing on an unclosed class or a trailing backslash
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '*' => {
+                    // Consecutive stars behave like a single one
+                    if tokens.last() == Some(&WildcardToken::AnyRun) {
+                        continue;
+                    }
+                    WildcardToken::AnyRun
+                }
+                '?' => WildcardToken::AnyChar,
+                '\\' => match chars.next() {
+                    Some(escaped) => WildcardToken::Literal(escaped),
+                    None => return Err(format!("trailing backslash in pattern {:?}", pattern)),
+                },
+                '[' => {
+                    let negated = matches!(chars.peek(), Some('!') | Some('^'));
+                    if negated {
+                        chars.next();
+                    }
+                    let mut ranges = Vec::new();
+                    let mut closed = false;
+                    let mut first = true;
+                    while let Some(c) = chars.next() {
+                        let lo = match c {
+                            ']' if !first => {
+                                closed = true;
+                                break;
+                            }
+                            '\\' => match chars.next() {
+                                Some(escaped) => escaped,
+                                None => break,
+                            },
+                            c => c,
+                        };
+                        first = false;
+                        let mut lookahead = chars.clone();
+                        let hi = match (lookahead.next(), lookahead.next()) {
+                            (Some('-'), Some(hi)) if hi != ']' => {
+                                chars.next();
+                                chars.next();
+                                hi
+                            }
+                            _ => lo,
+                        };
+                        ranges.push((lo, hi));
+                    }
+                    if !closed {
+                        return Err(format!("unclosed character class in pattern {:?}", pattern));
+                    }
+                    WildcardToken::Class { negated, ranges }
+                }
+                c => WildcardToken::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self {
+            source: pattern.to_string(),
+            tokens,
+        })
+    }
+
+    /// Returns true if the pattern matches the whole of `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        let tokens = &self.tokens;
+        let (mut pos, mut token) = (0, 0);
+        // Token index after the last `*` and the text position it was tried at
+        let mut backtrack: Option<(usize, usize)> = None;
+        loop {
+            if tokens.get(token) == Some(&WildcardToken::AnyRun) {
+                token += 1;
+                backtrack = Some((token, pos));
+                continue;
+            }
+            match text[pos..].chars().next() {
+                Some(c) if tokens.get(token).is_some_and(|t| t.matches(c)) => {
+                    pos += c.len_utf8();
+                    token += 1;
+                    continue;
+                }
+                None if token == tokens.len() => return true,
+                _ => {}
+            }
+            match backtrack {
+                Some((star_token, star_pos)) if star_pos < text.len() => {
+                    let skipped = text[star_pos..].chars().next().map_or(1, char::len_utf8);
+                    pos = star_pos + skipped;
+                    token = star_token;
+                    backtrack = Some((star_token, pos));
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// The source text of the pattern
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for Wildcard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,985 @@
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hint;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvError, SendError, Sender, TryRecvError};
+use std::sync::{
+    Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A lock that busy-waits instead of parking the thread
+///
+/// Only worth it for very short critical sections; anything that can block
+/// while holding the lock should use a `Mutex`. Never poisoned
+pub struct SpinLock<T: ?Sized> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: the lock ensures only one thread accesses the value at a time
+unsafe impl<T: ?Sized + Send> Send for SpinLock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// Create an unlocked spin lock
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consume the lock and return the value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> SpinLock<T> {
+    /// Spin until the lock is acquired
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            // Wait for the lock to look free before retrying the exchange
+            while self.locked.load(Ordering::Relaxed) {
+                hint::spin_loop();
+            }
+        }
+    }
+
+    /// Acquire the lock if it is free right now
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| SpinLockGuard {
+                lock: self,
+                _not_auto: PhantomData,
+            })
+    }
+
+    /// Returns true if the lock is currently held
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Get mutable access without locking when the lock itself is unique
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for SpinLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for SpinLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_tuple("SpinLock").field(&&*guard).finish(),
+            None => f.write_str("SpinLock(<locked>)"),
+        }
+    }
+}
+
+/// Releases a `SpinLock` when dropped
+///
+/// Like `MutexGuard`, the guard can only be shared between threads when `T`
+/// is `Sync`
+///
+/// ```compile_fail
+/// use rs_mytools::sync::SpinLock;
+/// use std::cell::Cell;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let lock = SpinLock::new(Cell::new(0));
+/// let guard = lock.lock();
+/// assert_sync(&guard);
+/// ```
+pub struct SpinLockGuard<'a, T: ?Sized> {
+    lock: &'a SpinLock<T>,
+    // Opts out of the automatic `Send` and `Sync` impls, which would only require `T: Send`
+    _not_auto: PhantomData<*const ()>,
+}
+
+// SAFETY: sending the guard hands unique access to the value to another thread
+unsafe impl<T: ?Sized + Send> Send for SpinLockGuard<'_, T> {}
+// SAFETY: a shared guard only gives out `&T`, which is fine across threads when `T: Sync`
+unsafe impl<T: ?Sized + Sync> Sync for SpinLockGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the guard holds the lock
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard holds the lock
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+/// Extensions for locks that grant exclusive access
+pub trait LockExt<T: ?Sized> {
+    /// The guard returned while the lock is held
+    type Guard<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    /// Acquire the lock, panicking if it is poisoned
+    fn lock_unwrap(&self) -> Self::Guard<'_>;
+
+    /// Acquire the lock, recovering the guard if it is poisoned
+    fn lock_ignore_poison(&self) -> Self::Guard<'_>;
+
+    /// Run a function with the lock held, panicking if it is poisoned
+    fn with_lock<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.lock_unwrap())
+    }
+
+    /// Run a function with the lock held, recovering it if it is poisoned
+    fn with_lock_ignore_poison<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.lock_ignore_poison())
+    }
+}
+
+impl<T: ?Sized> LockExt<T> for Mutex<T> {
+    type Guard<'a> = MutexGuard<'a, T> where Self: 'a;
+
+    fn lock_unwrap(&self) -> MutexGuard<'_, T> {
+        self.lock().expect("mutex poisoned")
+    }
+
+    fn lock_ignore_poison(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: ?Sized> LockExt<T> for RwLock<T> {
+    type Guard<'a> = RwLockWriteGuard<'a, T> where Self: 'a;
+
+    fn lock_unwrap(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().expect("rwlock poisoned")
+    }
+
+    fn lock_ignore_poison(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: ?Sized> LockExt<T> for SpinLock<T> {
+    type Guard<'a> = SpinLockGuard<'a, T> where Self: 'a;
+
+    fn lock_unwrap(&self) -> SpinLockGuard<'_, T> {
+        self.lock()
+    }
+
+    fn lock_ignore_poison(&self) -> SpinLockGuard<'_, T> {
+        self.lock()
+    }
+}
+
+/// Extensions for the shared side of an `RwLock`
+pub trait RwLockExt<T: ?Sized> {
+    /// Acquire a read lock, panicking if it is poisoned
+    fn read_unwrap(&self) -> RwLockReadGuard<'_, T>;
+
+    /// Acquire a read lock, recovering the guard if it is poisoned
+    fn read_ignore_poison(&self) -> RwLockReadGuard<'_, T>;
+
+    /// Run a function with a read lock held, panicking if it is poisoned
+    fn with_read<R, F: FnOnce(&T) -> R>(&self, f: F) -> R;
+
+    /// Acquire a read lock and project it onto part of the value
+    fn read_map<U: ?Sized, F>(&self, f: F) -> MappedGuard<RwLockReadGuard<'_, T>, U>
+    where
+        F: FnOnce(&T) -> &U;
+
+    /// Acquire a write lock and project it onto part of the value
+    fn write_map<U: ?Sized, F>(&self, f: F) -> MappedGuard<RwLockWriteGuard<'_, T>, U>
+    where
+        F: FnOnce(&mut T) -> &mut U;
+}
+
+impl<T: ?Sized> RwLockExt<T> for RwLock<T> {
+    fn read_unwrap(&self) -> RwLockReadGuard<'_, T> {
+        self.read().expect("rwlock poisoned")
+    }
+
+    fn read_ignore_poison(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with_read<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.read_unwrap())
+    }
+
+    fn read_map<U: ?Sized, F>(&self, f: F) -> MappedGuard<RwLockReadGuard<'_, T>, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        let guard = self.read_unwrap();
+        let value = NonNull::from(f(&guard));
+        MappedGuard {
+            value,
+            _invariant: PhantomData,
+            _guard: guard,
+        }
+    }
+
+    fn write_map<U: ?Sized, F>(&self, f: F) -> MappedGuard<RwLockWriteGuard<'_, T>, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let mut guard = self.lock_unwrap();
+        let value = NonNull::from(f(&mut guard));
+        MappedGuard {
+            value,
+            _invariant: PhantomData,
+            _guard: guard,
+        }
+    }
+}
+
+/// A lock guard projected onto part of the locked value
+///
+/// Keeps the original guard alive, so the lock stays held until this is
+/// dropped. Mutable access is only available when mapped from a write guard
+///
+/// The guard is invariant in `U`, so a write-mapped guard cannot be coerced
+/// to a shorter lifetime and used to store a short-lived borrow
+///
+/// ```compile_fail
+/// use rs_mytools::sync::MappedGuard;
+/// use std::sync::RwLockWriteGuard;
+///
+/// fn shorten<'a, 's>(
+///     guard: MappedGuard<RwLockWriteGuard<'a, &'static str>, &'static str>,
+/// ) -> MappedGuard<RwLockWriteGuard<'a, &'static str>, &'s str> {
+///     guard
+/// }
+/// ```
+pub struct MappedGuard<G, U: ?Sized> {
+    value: NonNull<U>,
+    // `NonNull` alone is covariant, which `DerefMut` makes unsound
+    _invariant: PhantomData<*mut U>,
+    _guard: G,
+}
+
+impl<G, U: ?Sized> Deref for MappedGuard<G, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        // SAFETY: the pointer comes from the locked value, which the guard
+        // keeps locked and does not move
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<'a, T: ?Sized, U: ?Sized> DerefMut for MappedGuard<RwLockWriteGuard<'a, T>, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        // SAFETY: as for `deref`, and the pointer came from a unique
+        // borrow through the write guard
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<G, U: ?Sized + fmt::Debug> fmt::Debug for MappedGuard<G, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Waits for a group of threads to finish
+///
+/// Every clone is a participant, and dropping a clone signals that it is
+/// done. `wait` gives up the caller's own participation and blocks until
+/// every other clone has been dropped
+pub struct WaitGroup {
+    inner: Arc<WaitGroupInner>,
+}
+
+struct WaitGroupInner {
+    count: Mutex<usize>,
+    done: Condvar,
+}
+
+impl WaitGroup {
+    /// Create a group with the caller as its only participant
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(WaitGroupInner {
+                count: Mutex::new(1),
+                done: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Block until every other participant has been dropped
+    pub fn wait(self) {
+        let inner = Arc::clone(&self.inner);
+        drop(self);
+        let mut count = inner.count.lock_ignore_poison();
+        while *count > 0 {
+            count = inner.done.wait(count).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// The number of participants that have not finished yet
+    pub fn pending(&self) -> usize {
+        *self.inner.count.lock_ignore_poison()
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for WaitGroup {
+    fn clone(&self) -> Self {
+        *self.inner.count.lock_ignore_poison() += 1;
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl Drop for WaitGroup {
+    fn drop(&mut self) {
+        let mut count = self.inner.count.lock_ignore_poison();
+        *count -= 1;
+        if *count == 0 {
+            self.inner.done.notify_all();
+        }
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroup").field("pending", &self.pending()).finish()
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// What `ThreadPool::join` and `ThreadPool::scope` do when a task panicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// Re-raise the first panic on the waiting thread
+    #[default]
+    Propagate,
+    /// Swallow panics; they are still counted by `ThreadPool::panic_count`
+    Ignore,
+}
+
+/// Tracks unfinished tasks and the first panic among them
+#[derive(Default)]
+struct TaskTracker {
+    pending: Mutex<usize>,
+    done: Condvar,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    panics: AtomicUsize,
+}
+
+impl TaskTracker {
+    fn start(&self) {
+        *self.pending.lock_ignore_poison() += 1;
+    }
+
+    fn finish(&self, result: thread::Result<()>) {
+        if let Err(payload) = result {
+            self.panics.fetch_add(1, Ordering::Relaxed);
+            self.panic.lock_ignore_poison().get_or_insert(payload);
+        }
+        let mut pending = self.pending.lock_ignore_poison();
+        *pending -= 1;
+        if *pending == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn wait(&self) {
+        let mut pending = self.pending.lock_ignore_poison();
+        while *pending > 0 {
+            pending = self.done.wait(pending).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    fn wrap<F: FnOnce() + Send>(self: &Arc<Self>, f: F) -> impl FnOnce() + Send {
+        self.start();
+        let tracker = Arc::clone(self);
+        move || tracker.finish(panic::catch_unwind(AssertUnwindSafe(f)))
+    }
+
+    fn check(&self, policy: PanicPolicy) {
+        let payload = self.panic.lock_ignore_poison().take();
+        if let (Some(payload), PanicPolicy::Propagate) = (payload, policy) {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// A fixed set of worker threads that run queued tasks
+///
+/// Panicking tasks do not take down their worker. Dropping the pool finishes
+/// the queued tasks and then joins the workers
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    tracker: Arc<TaskTracker>,
+    policy: PanicPolicy,
+}
+
+impl ThreadPool {
+    /// Start a pool with `threads` workers, panicking if `threads` is zero
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0, "a thread pool needs at least one thread");
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads)
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("pool-worker-{}", index))
+                    .spawn(move || loop {
+                        let job = receiver.lock_ignore_poison().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("failed to spawn pool worker")
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            workers,
+            tracker: Arc::new(TaskTracker::default()),
+            policy: PanicPolicy::default(),
+        }
+    }
+
+    /// Start a pool with one worker per available CPU
+    pub fn with_available_parallelism() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Choose how panics in tasks are reported
+    pub fn panic_policy(mut self, policy: PanicPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The number of worker threads
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// The number of tasks that have panicked so far
+    pub fn panic_count(&self) -> usize {
+        self.tracker.panics.load(Ordering::Relaxed)
+    }
+
+    /// Queue a task to run on a worker
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, f: F) {
+        self.submit(Box::new(self.tracker.wrap(f)));
+    }
+
+    /// Block until every task queued with `execute` has finished
+    ///
+    /// Under `PanicPolicy::Propagate` this re-raises the first panic since
+    /// the previous `join`
+    pub fn join(&self) {
+        self.tracker.wait();
+        self.tracker.check(self.policy);
+    }
+
+    /// Run tasks that may borrow from the caller's stack
+    ///
+    /// Every task spawned on the scope has finished by the time this returns,
+    /// even if `f` panics. Do not call this from a task running on the same
+    /// pool, as it can deadlock once every worker is waiting
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope PoolScope<'scope, 'env>) -> R,
+    {
+        let scope = PoolScope {
+            sender: self.sender.clone().expect("pool is running"),
+            tracker: Arc::new(TaskTracker::default()),
+            _scope: PhantomData,
+            _env: PhantomData,
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.tracker.wait();
+        self.tracker
+            .panics
+            .fetch_add(scope.tracker.panics.load(Ordering::Relaxed), Ordering::Relaxed);
+        match result {
+            Ok(value) => {
+                scope.tracker.check(self.policy);
+                value
+            }
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    fn submit(&self, job: Job) {
+        self.sender
+            .as_ref()
+            .expect("pool is running")
+            .send(job)
+            .expect("pool workers have stopped");
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPool")
+            .field("threads", &self.workers.len())
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+/// Spawns tasks onto a `ThreadPool` that may borrow from the enclosing scope
+pub struct PoolScope<'scope, 'env: 'scope> {
+    sender: Sender<Job>,
+    tracker: Arc<TaskTracker>,
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> PoolScope<'scope, '_> {
+    /// Queue a task that may borrow anything outliving the scope
+    pub fn spawn<F: FnOnce() + Send + 'scope>(&'scope self, f: F) {
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(self.tracker.wrap(f));
+        // SAFETY: `ThreadPool::scope` waits for every task spawned here before
+        // returning, so nothing the job borrows is dropped while it can run
+        let job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        self.sender.send(job).expect("pool workers have stopped");
+    }
+}
+
+/// Extensions for `mpsc` receivers
+pub trait ChannelExt<T> {
+    /// Iterate over messages until none arrives within `timeout` or the channel disconnects
+    fn recv_timeout_iter(&self, timeout: Duration) -> RecvTimeoutIter<'_, T>;
+
+    /// Take every message that is already queued without blocking
+    fn drain_available(&self) -> Vec<T>;
+}
+
+impl<T> ChannelExt<T> for Receiver<T> {
+    fn recv_timeout_iter(&self, timeout: Duration) -> RecvTimeoutIter<'_, T> {
+        RecvTimeoutIter {
+            receiver: self,
+            timeout,
+        }
+    }
+
+    fn drain_available(&self) -> Vec<T> {
+        self.try_iter().collect()
+    }
+}
+
+/// Iterator returned by `ChannelExt::recv_timeout_iter`
+pub struct RecvTimeoutIter<'a, T> {
+    receiver: &'a Receiver<T>,
+    timeout: Duration,
+}
+
+impl<T> Iterator for RecvTimeoutIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv_timeout(self.timeout).ok()
+    }
+}
+
+/// A message received by `select2`, tagged with the receiver it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selected<A, B> {
+    First(A),
+    Second(B),
+}
+
+/// Wait for a message on either of two receivers
+///
+/// Std channels have no native select, so this polls both with a short
+/// backoff. A disconnected receiver is skipped; the error is returned once
+/// both have disconnected
+pub fn select2<A, B>(first: &Receiver<A>, second: &Receiver<B>) -> Result<Selected<A, B>, RecvError> {
+    let mut backoff = Duration::from_micros(10);
+    loop {
+        let first_state = match first.try_recv() {
+            Ok(message) => return Ok(Selected::First(message)),
+            Err(state) => state,
+        };
+        let second_state = match second.try_recv() {
+            Ok(message) => return Ok(Selected::Second(message)),
+            Err(state) => state,
+        };
+        if first_state == TryRecvError::Disconnected && second_state == TryRecvError::Disconnected {
+            return Err(RecvError);
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_millis(5));
+    }
+}
+
+/// Create a channel that keeps only the newest `capacity` messages
+///
+/// Sending never blocks: when the buffer is full the oldest message is
+/// dropped to make room. Panics if `capacity` is zero
+pub fn bounded_latest<T>(capacity: usize) -> (LatestSender<T>, LatestReceiver<T>) {
+    assert!(capacity > 0, "capacity must be greater than 0");
+    let shared = Arc::new(LatestShared {
+        state: Mutex::new(LatestState {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+            dropped: 0,
+        }),
+        ready: Condvar::new(),
+        capacity,
+    });
+    (
+        LatestSender {
+            shared: Arc::clone(&shared),
+        },
+        LatestReceiver { shared },
+    )
+}
+
+struct LatestShared<T> {
+    state: Mutex<LatestState<T>>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+struct LatestState<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receiver_alive: bool,
+    dropped: usize,
+}
+
+/// Sending half of a `bounded_latest` channel
+pub struct LatestSender<T> {
+    shared: Arc<LatestShared<T>>,
+}
+
+impl<T> LatestSender<T> {
+    /// Queue a message, returning the oldest one if it had to be dropped
+    ///
+    /// Fails with the message if the receiver is gone
+    pub fn send(&self, message: T) -> Result<Option<T>, SendError<T>> {
+        let mut state = self.shared.state.lock_ignore_poison();
+        if !state.receiver_alive {
+            return Err(SendError(message));
+        }
+        let evicted = if state.queue.len() == self.shared.capacity {
+            state.dropped += 1;
+            state.queue.pop_front()
+        } else {
+            None
+        };
+        state.queue.push_back(message);
+        self.shared.ready.notify_one();
+        Ok(evicted)
+    }
+}
+
+impl<T> Clone for LatestSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock_ignore_poison().senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for LatestSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock_ignore_poison();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.ready.notify_all();
+        }
+    }
+}
+
+/// Receiving half of a `bounded_latest` channel
+pub struct LatestReceiver<T> {
+    shared: Arc<LatestShared<T>>,
+}
+
+impl<T> LatestReceiver<T> {
+    /// Block until a message arrives, failing once every sender is gone and the buffer is empty
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.shared.state.lock_ignore_poison();
+        loop {
+            if let Some(message) = state.queue.pop_front() {
+                return Ok(message);
+            }
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+            state = self.shared.ready.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Take the oldest buffered message without blocking
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.state.lock_ignore_poison();
+        match state.queue.pop_front() {
+            Some(message) => Ok(message),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Take every buffered message without blocking
+    pub fn drain_available(&self) -> Vec<T> {
+        self.shared.state.lock_ignore_poison().queue.drain(..).collect()
+    }
+
+    /// The number of messages dropped so far to make room for newer ones
+    pub fn dropped_count(&self) -> usize {
+        self.shared.state.lock_ignore_poison().dropped
+    }
+}
+
+impl<T> Drop for LatestReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock_ignore_poison().receiver_alive = false;
+    }
+}
+
+/// A cloneable handle to a value behind `Arc<Mutex<T>>`
+///
+/// Mirrors the `SelfRef` API for values shared between threads. Clones
+/// refer to the same value
+pub struct Shared<T> {
+    inner: Arc<Mutex<T>>,
+}
+
+impl<T> Shared<T> {
+    /// Create a new shared value
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(value)),
+        }
+    }
+
+    /// Lock the value for shared access, panicking if the lock is poisoned
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.inner.lock_unwrap()
+    }
+
+    /// Lock the value for mutable access, panicking if the lock is poisoned
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.inner.lock_unwrap()
+    }
+
+    /// Apply a function to the value that returns a result
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.inner.lock_unwrap())
+    }
+
+    /// Apply a function that mutates the value
+    pub fn with_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.inner.lock_unwrap())
+    }
+
+    /// Apply a function without blocking, failing if the lock is held or poisoned
+    pub fn try_with<R, F: FnOnce(&T) -> R>(&self, f: F) -> Result<R, TryLockError<()>> {
+        match self.inner.try_lock() {
+            Ok(guard) => Ok(f(&guard)),
+            Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+            Err(TryLockError::Poisoned(_)) => Err(TryLockError::Poisoned(PoisonError::new(()))),
+        }
+    }
+
+    /// Returns true if both handles refer to the same value
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Take the value back if this is the last handle
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        Arc::try_unwrap(self.inner)
+            .map(|mutex| mutex.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|inner| Self { inner })
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.try_lock() {
+            Ok(guard) => f.debug_tuple("Shared").field(&*guard).finish(),
+            Err(_) => f.write_str("Shared(<locked>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKS: usize = 16;
+
+    #[test]
+    fn pool_scope_tasks_can_borrow_the_stack() {
+        let pool = ThreadPool::new(4);
+        let mut results = vec![0; TASKS];
+        pool.scope(|scope| {
+            for (index, slot) in results.iter_mut().enumerate() {
+                scope.spawn(move || {
+                    thread::sleep(Duration::from_millis(1));
+                    *slot = index * 2;
+                });
+            }
+        });
+        assert_eq!(results, (0..TASKS).map(|index| index * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pool_scope_joins_tasks_when_the_closure_panics() {
+        let pool = ThreadPool::new(2);
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                for _ in 0..TASKS {
+                    scope.spawn(|| {
+                        thread::sleep(Duration::from_millis(5));
+                        finished.fetch_add(1, Ordering::SeqCst);
+                    });
+                }
+                panic!("scope closure failed");
+            })
+        }));
+        let payload = result.expect_err("the closure's panic is re-raised");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"scope closure failed"));
+        assert_eq!(finished.load(Ordering::SeqCst), TASKS);
+    }
+
+    #[test]
+    fn pool_scope_propagates_task_panics_after_joining() {
+        let pool = ThreadPool::new(2);
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                scope.spawn(|| panic!("task failed"));
+                for _ in 0..TASKS {
+                    scope.spawn(|| {
+                        thread::sleep(Duration::from_millis(1));
+                        finished.fetch_add(1, Ordering::SeqCst);
+                    });
+                }
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(finished.load(Ordering::SeqCst), TASKS);
+        assert_eq!(pool.panic_count(), 1);
+    }
+
+    #[test]
+    fn pool_ignore_policy_counts_panics_and_keeps_workers() {
+        let pool = ThreadPool::new(1).panic_policy(PanicPolicy::Ignore);
+        let ran = Arc::new(AtomicUsize::new(0));
+        pool.execute(|| panic!("task failed"));
+        let counter = Arc::clone(&ran);
+        pool.execute(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        pool.join();
+        let value = pool.scope(|scope| {
+            scope.spawn(|| panic!("scoped task failed"));
+            7
+        });
+        assert_eq!(value, 7);
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.panic_count(), 2);
+    }
+
+    #[test]
+    fn mapped_guards_project_and_hold_the_lock() {
+        let lock = RwLock::new((String::from("name"), 1));
+        {
+            let mut count = lock.write_map(|pair| &mut pair.1);
+            *count += 1;
+            assert!(lock.try_read().is_err());
+        }
+        let name = lock.read_map(|pair| pair.0.as_str());
+        assert_eq!(&*name, "name");
+        assert!(lock.try_write().is_err());
+        assert_eq!(lock.read_unwrap().1, 2);
+        drop(name);
+        assert!(lock.try_write().is_ok());
+    }
+
+    #[test]
+    fn pool_join_propagates_the_first_panic_once() {
+        let pool = ThreadPool::new(2);
+        pool.execute(|| panic!("task failed"));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join()));
+        assert!(result.is_err());
+        pool.join();
+    }
+}